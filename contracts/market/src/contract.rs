@@ -1,24 +1,34 @@
-use std::{collections::HashMap, str::FromStr};
+use std::collections::{BTreeMap, HashMap};
 
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
-    QueryRequest, Response, StdError, StdResult, Storage, Uint128, WasmMsg, WasmQuery,
+    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    Event, Fraction, MessageInfo, Order, QueryRequest, Reply, Response, StdError, StdResult,
+    Storage, SubMsg, Uint128, WasmMsg, WasmQuery,
 };
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, Expiration};
+use cw_storage_plus::Map;
 
 use crate::{
     errors::ContractError,
     msgs::{
-        is_native, BridgeMsgInfo, Cw20MsgType, ExecuteMsg, GateCollectMsgsAllowed, InstantiateMsg,
-        MigrateMsg, Position, QueryMsg,
+        is_native, BridgeFeeResponse, BridgeFeeSplit, BridgeMsgInfo, Cw20MsgType, ExecuteMsg,
+        FlashLoanReceiverMsg, GateCollectMsgsAllowed, HealthResponse, InstantiateMsg, MigrateMsg,
+        MsgReplyID, Position, QueryMsg,
+    },
+    state::{
+        Approval, BorrowIndex, ChainConfig, CollateralCategory, CollateralConfig, FlashLoanState,
+        InterestConfig, APPROVALS, BORROW_INDEX, BRIDGE_ID, CATEGORIES, CHAINS_CONFIG,
+        CHAINS_CONTRACT, COLLATERALS_CONFIG, FLASH_LOAN, FLASH_LOAN_FEE, GATE, INTEREST_CONFIG,
+        LOAN_ASSET, LOAN_DENOM, ORACLE, OWNER, POSITIONS, POSITION_BORROW_INDEX, REMOTE_ADDRS,
+        RESERVES, SECONDS_PER_YEAR, TOTAL_DEPOSITS,
     },
-    state::{CHAINS_CONTRACT, GATE, OWNER, POSITIONS},
 };
 
 use cw20_icg_pkg::ExecuteMsg as Cw20_icg_ExecuteMsg;
 use gate_pkg::{ExecuteMsg as GateExecuteMsg, GateMsg, GateRequest, Permission, SendNativeInfo};
+use oracle::msgs::QueryMsg as OracleQueryMsg;
 
 // --- ENTRY POINTS ---
 
@@ -44,47 +54,205 @@ pub fn execute(
     match msg {
         ExecuteMsg::Receive(msg) => run_receive_cw20(deps, env, info.sender, msg),
         ExecuteMsg::RegisterGate { contract } => run_register_gate(deps, info.sender, contract),
+        ExecuteMsg::RegisterOracle {
+            contract,
+            loan_asset,
+        } => run_register_oracle(deps, info.sender, contract, loan_asset),
+        ExecuteMsg::RegisterCollateral {
+            denom,
+            oracle_asset,
+            ltv,
+        } => run_register_collateral(deps, info.sender, denom, oracle_asset, ltv),
+        ExecuteMsg::RegisterCategory {
+            name,
+            collaterals,
+            ltv,
+            liquidation_threshold,
+        } => run_register_category(
+            deps,
+            info.sender,
+            name,
+            collaterals,
+            ltv,
+            liquidation_threshold,
+        ),
+        ExecuteMsg::SetPositionCategory { category } => {
+            run_set_position_category(deps, env, info.sender, category)
+        }
         ExecuteMsg::GateSetPermission { contract, chain } => {
             run_gate_set_permission(deps, info.sender, contract, chain)
         }
-        ExecuteMsg::Withdraw { denom, amount } => run_withdraw(deps, info.sender, denom, amount),
-        ExecuteMsg::IncreaseLoan { amount } => run_increase_loan(deps, info.sender, amount),
-        ExecuteMsg::RepayLoan { amount } => run_repay_loan(deps, info.sender, amount),
-        // --- GATE MSGS ---
-        ExecuteMsg::ReceiveGateMsg(msg) => gate_receive_msg(deps, info, msg),
-        ExecuteMsg::Deposit {} => {
-            let coin = onecoin(info.funds)?.unwrap();
-            user_deposit(deps.storage, &info.sender, coin.amount, coin.denom)
+        ExecuteMsg::GateRemovePermission { contract, chain } => {
+            run_gate_remove_permission(deps, info.sender, contract, chain)
         }
+        ExecuteMsg::RemoveChain { chain } => run_remove_chain(deps, info.sender, chain),
+        ExecuteMsg::SetChainConfig {
+            chain,
+            timeout,
+            fee_per_request,
+            bridge_fee,
+        } => run_set_chain_config(
+            deps,
+            info.sender,
+            chain,
+            timeout,
+            fee_per_request,
+            bridge_fee,
+        ),
+        ExecuteMsg::WithdrawReserves {
+            denom,
+            amount,
+            recipient,
+        } => run_withdraw_reserves(deps, info.sender, denom, amount, recipient),
+        ExecuteMsg::SetInterestConfig {
+            borrow_rate,
+            reserve_factor,
+        } => run_set_interest_config(deps, env, info.sender, borrow_rate, reserve_factor),
+        ExecuteMsg::SetFlashLoanFee { fee } => run_set_flash_loan_fee(deps, info.sender, fee),
+        ExecuteMsg::FlashLoan {
+            denom,
+            amount,
+            callback,
+        } => run_flash_loan(deps, env, info.sender, denom, amount, callback),
+        ExecuteMsg::ApproveOperator {
+            operator,
+            allowances,
+            expires,
+        } => run_approve_operator(deps, env, info.sender, operator, allowances, expires),
+        ExecuteMsg::RevokeOperator { operator } => run_revoke_operator(deps, info.sender, operator),
+        ExecuteMsg::SetRemoteAddr { chain, remote_addr } => {
+            run_set_remote_addr(deps, info.sender, chain, remote_addr)
+        }
+        ExecuteMsg::Withdraw {
+            denom,
+            amount,
+            on_behalf_of,
+        } => run_withdraw(deps, env, info.sender, denom, amount, on_behalf_of),
+        ExecuteMsg::IncreaseLoan {
+            amount,
+            on_behalf_of,
+        } => run_increase_loan(deps, env, info.sender, amount, on_behalf_of),
+        ExecuteMsg::RepayLoan {
+            amount,
+            on_behalf_of,
+        } => run_repay_loan(deps, env, info.sender, amount, on_behalf_of),
+        // --- GATE MSGS ---
+        ExecuteMsg::ReceiveGateMsg(msg) => gate_receive_msg(deps, env, info, msg),
+        ExecuteMsg::Deposit {} => run_deposit(deps, info.sender, info.funds),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Position { user } => to_binary(&qy_position(deps, user).unwrap()),
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    let binary = match msg {
+        QueryMsg::Position { user } => to_binary(&qy_position(deps, env, user)?),
+        QueryMsg::Health { user } => to_binary(&qy_health(deps, env, user)?),
+        QueryMsg::SimulateBorrow { user, amount } => {
+            to_binary(&qy_simulate_borrow(deps, env, user, amount)?)
+        }
+        QueryMsg::SimulateWithdraw {
+            user,
+            denom,
+            amount,
+        } => to_binary(&qy_simulate_withdraw(deps, env, user, denom, amount)?),
+        QueryMsg::Category { name } => to_binary(&qy_category(deps, name)?),
+        QueryMsg::Categories {} => to_binary(&qy_categories(deps)?),
+        QueryMsg::Reserve { denom } => to_binary(&qy_reserve(deps, denom)?),
+        QueryMsg::InterestConfig {} => to_binary(&qy_interest_config(deps)?),
+        QueryMsg::Approval { user, operator } => to_binary(&qy_approval(deps, user, operator)?),
+        QueryMsg::Approvals { user } => to_binary(&qy_approvals(deps, user)?),
+        QueryMsg::RemoteAddr { user, chain } => to_binary(&qy_remote_addr(deps, user, chain)?),
+        QueryMsg::RemoteContracts { chain } => to_binary(&qy_remote_contracts(deps, chain)?),
+        QueryMsg::ChainConfig { chain } => to_binary(&qy_chain_config(deps, chain)?),
+        QueryMsg::BridgeFee {
+            chain,
+            cw20_collaterals,
+        } => to_binary(&qy_bridge_fee(deps, chain, cw20_collaterals)?),
+    }?;
+
+    Ok(binary)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match MsgReplyID::from_repr(reply.id) {
+        Some(MsgReplyID::FlashLoan) => reply_flash_loan(deps, env),
+        None => Err(ContractError::Std(StdError::generic_err(format!(
+            "invalid reply id {}",
+            reply.id
+        )))),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    migrate_chains_contract(deps.storage)?;
+    migrate_total_deposits(deps.storage)?;
+
     Ok(Response::new())
 }
 
+/// `CHAINS_CONTRACT` used to store a single remote contract for every chain.
+/// Rewrite every legacy entry as a list with one contract. The entries already migrated are skipped.
+fn migrate_chains_contract(storage: &mut dyn Storage) -> StdResult<()> {
+    const LEGACY_CHAINS_CONTRACT: Map<String, String> = Map::new("chains_contracts");
+
+    // The values can't be deserialized before the migration, only the raw keys are read
+    let chains = CHAINS_CONTRACT
+        .keys_raw(storage, None, None, Order::Ascending)
+        .map(|key| String::from_utf8(key).map_err(StdError::from))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    for chain in chains {
+        if CHAINS_CONTRACT.load(storage, chain.clone()).is_ok() {
+            continue;
+        }
+
+        let contract = LEGACY_CHAINS_CONTRACT.load(storage, chain.clone())?;
+
+        CHAINS_CONTRACT.save(storage, chain, &vec![contract])?;
+    }
+
+    Ok(())
+}
+
+/// Compute `TOTAL_DEPOSITS` from the collaterals of the current positions.
+fn migrate_total_deposits(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut totals: HashMap<String, Uint128> = HashMap::new();
+
+    for item in POSITIONS.range(storage, None, None, Order::Ascending) {
+        let (_, position) = item?;
+
+        for (denom, amount) in position.collaterals {
+            let total = totals.entry(denom).or_default();
+            *total = total.checked_add(amount)?;
+        }
+    }
+
+    for (denom, total) in totals {
+        TOTAL_DEPOSITS.save(storage, denom, &total)?;
+    }
+
+    Ok(())
+}
+
 // --- RUN ---
 
 fn gate_receive_msg(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     msg: GateMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        GateMsg::RequestFailed { request } => run_gate_revert_request(deps, info.sender, request),
+        GateMsg::RequestFailed { request } => {
+            run_gate_revert_request(deps, env, info.sender, request)
+        }
         GateMsg::ReceivedMsg { sender, msg } => {
-            run_gate_receive_msg(deps, info.sender, sender, msg)
+            run_gate_receive_msg(deps, env, info.sender, sender, msg)
         }
         GateMsg::CollectRequests { sender, msg } => {
-            run_gate_collect_msgs(deps, info.funds, info.sender, sender, msg)
+            run_gate_collect_msgs(deps, env, info.funds, info.sender, sender, msg)
         }
         _ => Err(ContractError::Std(StdError::generic_err(format!(
             "{:?} not implemented on mock_market",
@@ -100,15 +268,34 @@ fn run_receive_cw20(
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     match from_binary::<Cw20MsgType>(&cw20_msg.msg)? {
-        Cw20MsgType::Deposit {} => user_deposit(
-            deps.storage,
-            &deps.api.addr_validate(cw20_msg.sender.as_str())?,
-            cw20_msg.amount,
-            cw20_address.to_string(),
-        ),
+        Cw20MsgType::Deposit {} => {
+            let user = deps.api.addr_validate(cw20_msg.sender.as_str())?;
+
+            user_deposit(
+                deps.storage,
+                &user,
+                cw20_msg.amount,
+                cw20_address.to_string(),
+            )?;
+
+            Ok(Response::new().add_event(market_event(
+                "deposit",
+                &user,
+                cw20_address,
+                cw20_msg.amount,
+            )))
+        }
     }
 }
 
+fn run_deposit(deps: DepsMut, user: Addr, funds: Vec<Coin>) -> Result<Response, ContractError> {
+    let coin = onecoin(funds)?.ok_or(StdError::generic_err("No coin received"))?;
+
+    user_deposit(deps.storage, &user, coin.amount, coin.denom.clone())?;
+
+    Ok(Response::new().add_event(market_event("deposit", &user, coin.denom, coin.amount)))
+}
+
 fn run_register_gate(
     deps: DepsMut,
     sender: Addr,
@@ -121,6 +308,124 @@ fn run_register_gate(
     Ok(Response::new())
 }
 
+fn run_register_oracle(
+    deps: DepsMut,
+    sender: Addr,
+    contract: Addr,
+    loan_asset: String,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    ORACLE.save(deps.storage, &contract)?;
+    LOAN_ASSET.save(deps.storage, &loan_asset)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_oracle")
+        .add_attribute("contract", contract)
+        .add_attribute("loan_asset", loan_asset))
+}
+
+fn run_register_collateral(
+    deps: DepsMut,
+    sender: Addr,
+    denom: String,
+    oracle_asset: String,
+    ltv: Decimal,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if ltv > Decimal::one() {
+        return Err(ContractError::InvalidLtv { ltv });
+    }
+
+    COLLATERALS_CONFIG.save(
+        deps.storage,
+        denom.clone(),
+        &CollateralConfig {
+            oracle_asset: oracle_asset.clone(),
+            ltv,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_collateral")
+        .add_attribute("denom", denom)
+        .add_attribute("oracle_asset", oracle_asset)
+        .add_attribute("ltv", ltv.to_string()))
+}
+
+fn run_register_category(
+    deps: DepsMut,
+    sender: Addr,
+    name: String,
+    collaterals: Vec<String>,
+    ltv: Decimal,
+    liquidation_threshold: Decimal,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if ltv > Decimal::one() {
+        return Err(ContractError::InvalidLtv { ltv });
+    }
+
+    if liquidation_threshold < ltv || liquidation_threshold > Decimal::one() {
+        return Err(ContractError::InvalidLiquidationThreshold {
+            ltv,
+            liquidation_threshold,
+        });
+    }
+
+    for denom in &collaterals {
+        if !COLLATERALS_CONFIG.has(deps.storage, denom.to_owned()) {
+            return Err(ContractError::CollateralNotFound {});
+        }
+    }
+
+    CATEGORIES.save(
+        deps.storage,
+        name.clone(),
+        &CollateralCategory {
+            collaterals,
+            ltv,
+            liquidation_threshold,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_category")
+        .add_attribute("name", name)
+        .add_attribute("ltv", ltv.to_string())
+        .add_attribute("liquidation_threshold", liquidation_threshold.to_string()))
+}
+
+fn run_set_position_category(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    category: Option<String>,
+) -> Result<Response, ContractError> {
+    let interest_event = accrue_interest(deps.storage, &env, &user)?;
+
+    let mut position = POSITIONS
+        .may_load(deps.storage, user.clone())?
+        .ok_or(ContractError::UserNotFound {})?;
+
+    position.category = category.clone();
+
+    check_category(deps.storage, &position)?;
+
+    // Leaving or changing the category change the borrow limit of the position
+    check_borrow_limit(deps.as_ref(), &position)?;
+
+    POSITIONS.save(deps.storage, user.clone(), &position)?;
+
+    Ok(Response::new()
+        .add_events(interest_event)
+        .add_attribute("action", "set_position_category")
+        .add_attribute("user", user)
+        .add_attribute("category", category.unwrap_or_default()))
+}
+
 fn run_gate_set_permission(
     deps: DepsMut,
     sender: Addr,
@@ -129,27 +434,270 @@ fn run_gate_set_permission(
 ) -> Result<Response, ContractError> {
     onlyowner(deps.storage, &sender)?;
 
-    CHAINS_CONTRACT.save(deps.storage, chain.clone(), &contract)?;
+    let mut contracts = CHAINS_CONTRACT
+        .may_load(deps.storage, chain.clone())?
+        .unwrap_or_default();
 
-    let msg = CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute {
-        contract_addr: GATE.load(deps.storage)?.to_string(),
-        msg: to_binary(&GateExecuteMsg::SetPermission {
-            permission: Permission::Permissioned {
-                addresses: vec![contract.clone()],
-            },
-            chain,
-        })?,
-        funds: vec![],
-    });
+    if contracts.contains(&contract) {
+        return Err(ContractError::RemoteContractAlredyRegistered { contract, chain });
+    }
+
+    contracts.push(contract.clone());
+
+    CHAINS_CONTRACT.save(deps.storage, chain.clone(), &contracts)?;
 
     Ok(Response::new()
-        .add_message(msg)
+        .add_message(gate_set_permission_msg(deps.storage, contracts, chain)?)
         .add_attribute("action", "register_remote_contract")
         .add_attribute("value", contract))
 }
 
+fn run_gate_remove_permission(
+    deps: DepsMut,
+    sender: Addr,
+    contract: String,
+    chain: String,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    let mut contracts = CHAINS_CONTRACT
+        .may_load(deps.storage, chain.clone())?
+        .ok_or(ContractError::ChainNotRegistered {
+            chain: chain.clone(),
+        })?;
+
+    if !contracts.contains(&contract) {
+        return Err(ContractError::RemoteContractNotRegistered { contract, chain });
+    }
+
+    contracts.retain(|value| *value != contract);
+
+    if contracts.is_empty() {
+        return run_remove_chain(deps, sender, chain);
+    }
+
+    CHAINS_CONTRACT.save(deps.storage, chain.clone(), &contracts)?;
+
+    Ok(Response::new()
+        .add_message(gate_set_permission_msg(deps.storage, contracts, chain)?)
+        .add_attribute("action", "remove_remote_contract")
+        .add_attribute("value", contract))
+}
+
+fn run_remove_chain(deps: DepsMut, sender: Addr, chain: String) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if !CHAINS_CONTRACT.has(deps.storage, chain.clone()) {
+        return Err(ContractError::ChainNotRegistered { chain });
+    }
+
+    CHAINS_CONTRACT.remove(deps.storage, chain.clone());
+    CHAINS_CONFIG.remove(deps.storage, chain.clone());
+
+    Ok(Response::new()
+        .add_message(gate_set_permission_msg(
+            deps.storage,
+            vec![],
+            chain.clone(),
+        )?)
+        .add_attribute("action", "remove_chain")
+        .add_attribute("chain", chain))
+}
+
+fn run_set_chain_config(
+    deps: DepsMut,
+    sender: Addr,
+    chain: String,
+    timeout: Option<u64>,
+    fee_per_request: Option<Coin>,
+    bridge_fee: Option<Coin>,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if !CHAINS_CONTRACT.has(deps.storage, chain.clone()) {
+        return Err(ContractError::ChainNotRegistered { chain });
+    }
+
+    // The fees are paid with only one coin
+    if let (Some(fee_per_request), Some(bridge_fee)) = (&fee_per_request, &bridge_fee) {
+        if fee_per_request.denom != bridge_fee.denom {
+            return Err(ContractError::FeeDenomMismatch {});
+        }
+    }
+
+    CHAINS_CONFIG.save(
+        deps.storage,
+        chain.clone(),
+        &ChainConfig {
+            timeout,
+            fee_per_request,
+            bridge_fee,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_chain_config")
+        .add_attribute("chain", chain))
+}
+
+fn run_withdraw_reserves(
+    deps: DepsMut,
+    sender: Addr,
+    denom: String,
+    amount: Uint128,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if denom == LOAN_DENOM {
+        return Err(ContractError::LoanReservesNotWithdrawable {});
+    }
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let available = RESERVES
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default();
+
+    if available < amount {
+        return Err(ContractError::InsufficientReserves { available });
+    }
+
+    RESERVES.save(deps.storage, denom.clone(), &(available - amount))?;
+
+    Ok(Response::new()
+        .add_message(transfer_msg(&deps.as_ref(), &denom, amount, &recipient)?)
+        .add_attribute("action", "withdraw_reserves")
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+        .add_attribute("recipient", recipient))
+}
+
+fn run_set_interest_config(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    borrow_rate: Decimal,
+    reserve_factor: Decimal,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if reserve_factor > Decimal::one() {
+        return Err(ContractError::InvalidReserveFactor { reserve_factor });
+    }
+
+    // The interest accrued until now uses the previous rate
+    let borrow_index = current_borrow_index(deps.storage, &env)?;
+    BORROW_INDEX.save(deps.storage, &borrow_index)?;
+
+    INTEREST_CONFIG.save(
+        deps.storage,
+        &InterestConfig {
+            borrow_rate,
+            reserve_factor,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_interest_config")
+        .add_attribute("borrow_rate", borrow_rate.to_string())
+        .add_attribute("reserve_factor", reserve_factor.to_string()))
+}
+
+fn run_set_flash_loan_fee(
+    deps: DepsMut,
+    sender: Addr,
+    fee: Decimal,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    FLASH_LOAN_FEE.save(deps.storage, &fee)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_flash_loan_fee")
+        .add_attribute("fee", fee.to_string()))
+}
+
+fn run_flash_loan(
+    deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    denom: String,
+    amount: Uint128,
+    callback: Binary,
+) -> Result<Response, ContractError> {
+    if FLASH_LOAN.exists(deps.storage) {
+        return Err(ContractError::FlashLoanInProgress {});
+    }
+
+    let balance = query_balance(deps.as_ref(), &denom, &env.contract.address)?;
+
+    // The collaterals of the positions are not lent
+    let available = balance.saturating_sub(
+        TOTAL_DEPOSITS
+            .may_load(deps.storage, denom.clone())?
+            .unwrap_or_default(),
+    );
+
+    if available < amount {
+        return Err(ContractError::InsufficientLiquidity { available });
+    }
+
+    let fee = amount * FLASH_LOAN_FEE.may_load(deps.storage)?.unwrap_or_default();
+
+    FLASH_LOAN.save(
+        deps.storage,
+        &FlashLoanState {
+            receiver: receiver.clone(),
+            denom: denom.clone(),
+            amount,
+            fee,
+            expected_balance: balance.checked_add(fee)?,
+        },
+    )?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut funds: Vec<Coin> = vec![];
+
+    if is_native(&deps.as_ref(), &denom) {
+        funds.push(Coin {
+            denom: denom.clone(),
+            amount,
+        });
+    } else {
+        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: denom.clone(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: receiver.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    let callback_msg = WasmMsg::Execute {
+        contract_addr: receiver.to_string(),
+        msg: to_binary(&FlashLoanReceiverMsg::FlashLoanCallback {
+            denom: denom.clone(),
+            amount,
+            fee,
+            msg: callback,
+        })?,
+        funds,
+    };
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_submessage(SubMsg::reply_on_success(
+            callback_msg,
+            MsgReplyID::FlashLoan.repr(),
+        ))
+        .add_event(market_event("flash_loan", &receiver, denom, amount).add_attribute("fee", fee)))
+}
+
 fn run_gate_collect_msgs(
     deps: DepsMut,
+    env: Env,
     funds: Vec<Coin>,
     gate: Addr,
     sender: Addr,
@@ -163,48 +711,89 @@ fn run_gate_collect_msgs(
         GateCollectMsgsAllowed::BridgePosition {
             to_remote_addr,
             chain,
+            remote_contract,
             native_info,
+            timeout,
+            on_behalf_of,
         } => {
             let coin = onecoin(funds)?;
 
+            let operator = sender;
+            let sender = resolve_user(&deps, &env, &operator, on_behalf_of)?;
+
+            if sender != operator {
+                // Bridging moves the whole position, only operators without limits are allowed
+                if APPROVALS
+                    .load(deps.storage, (sender.clone(), operator))?
+                    .allowances
+                    .is_some()
+                {
+                    return Err(ContractError::OperatorAllowanceLimited {});
+                }
+
+                // As for the withdraws, the position can only be sent to its owner
+                if REMOTE_ADDRS
+                    .may_load(deps.storage, (sender.clone(), chain.clone()))?
+                    .as_ref()
+                    != Some(&to_remote_addr)
+                {
+                    return Err(ContractError::RemoteAddrNotRegistered { chain });
+                }
+            }
+
+            let remote_contract = load_remote_contract(deps.storage, &chain, remote_contract)?;
+
+            let chain_config = CHAINS_CONFIG
+                .may_load(deps.storage, chain.clone())?
+                .unwrap_or_default();
+
+            let interest_event = accrue_interest(deps.storage, &env, &sender)?;
+
             let position = POSITIONS.load(deps.storage, sender.clone())?;
 
+            check_category(deps.storage, &position)?;
+
             position.is_bridgable(&deps.as_ref())?;
 
-            // I want to equally divide the coin to send to gate contract for fee equally divide it for every collateral
-            let mut fund_per_collateral: Vec<Coin> = vec![];
-            let mut fund_bridge_position: Vec<Coin> = vec![];
-
-            if let Some(coin) = coin {
-                let mut qta_collaterals =
-                    Uint128::from_str(position.collaterals.len().to_string().as_str())?;
-
-                if native_info.is_some() {
-                    qta_collaterals -= Uint128::one();
-                };
-
-                let amount_per_collateral = coin
-                    .amount
-                    .checked_div(qta_collaterals + Uint128::one())
-                    .unwrap();
-                fund_per_collateral.push(Coin {
-                    denom: coin.denom.clone(),
-                    amount: amount_per_collateral,
-                });
-                fund_bridge_position.push(Coin {
-                    denom: coin.denom,
-                    amount: coin
-                        .amount
-                        .checked_sub(amount_per_collateral * qta_collaterals)
-                        .unwrap(),
-                })
+            let bridge_id = BRIDGE_ID.may_load(deps.storage)?.unwrap_or_default() + 1;
+
+            BRIDGE_ID.save(deps.storage, &bridge_id)?;
+
+            let events = bridge_events(
+                "bridge_out",
+                &sender,
+                &position,
+                BridgeChain::Dest(&chain),
+                bridge_id,
+            );
+
+            let cw20_collaterals = position
+                .collaterals
+                .keys()
+                .filter(|denom| !is_native(&deps.as_ref(), denom))
+                .count() as u64;
+
+            let has_native = position.collaterals.len() as u64 > cw20_collaterals;
+
+            match (has_native, &native_info) {
+                (true, None) => return Err(ContractError::NativeInfoRequired {}),
+                (false, Some(_)) => return Err(ContractError::NativeCollateralNotFound {}),
+                _ => {}
             }
 
+            let coin = take_bridge_fee(deps.storage, coin, cw20_collaterals, &chain_config)?;
+
+            let fee_split = split_bridge_fee(coin, cw20_collaterals, &chain_config)?;
+
+            let fund_per_collateral: Vec<Coin> = fee_split.per_cw20.into_iter().collect();
+            let mut fund_bridge_position: Vec<Coin> = fee_split.market.into_iter().collect();
+
             let mut msgs: Vec<CosmosMsg> = vec![];
 
             let mut remote_position = Position {
                 loan: position.loan,
                 collaterals: HashMap::new(),
+                category: position.category.clone(),
             };
 
             for (denom, amount) in position.clone().collaterals {
@@ -226,7 +815,9 @@ fn run_gate_collect_msgs(
                         }),
                     }
 
-                    let native_info = native_info.clone().unwrap();
+                    let native_info = native_info
+                        .clone()
+                        .ok_or(ContractError::NativeInfoRequired {})?;
 
                     remote_position
                         .collaterals
@@ -256,7 +847,7 @@ fn run_gate_collect_msgs(
                         contract_addr: denom,
                         msg: to_binary(&Cw20_icg_ExecuteMsg::GateBridge {
                             chain: chain.clone(),
-                            remote_receiver: CHAINS_CONTRACT.load(deps.storage, chain.clone())?,
+                            remote_receiver: remote_contract.clone(),
                             amount,
                         })?,
                         funds: fund_per_collateral.clone(),
@@ -264,82 +855,217 @@ fn run_gate_collect_msgs(
                 }
             }
 
+            for (denom, amount) in &position.collaterals {
+                decrease_total_deposits(deps.storage, denom, *amount)?;
+            }
+
             msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
                 contract_addr: GATE.load(deps.storage)?.to_string(),
                 msg: to_binary(&GateExecuteMsg::SendRequests {
                     requests: vec![GateRequest::SendMsg {
                         msg: to_binary(&BridgeMsgInfo {
+                            bridge_id,
+                            chain: chain.clone(),
                             sender: sender.to_string(),
                             receiver: to_remote_addr,
                             src_position: position,
                             dest_position: remote_position,
                         })?,
-                        to_contract: CHAINS_CONTRACT.load(deps.storage, chain.clone())?,
+                        to_contract: remote_contract,
                         send_native,
                     }],
                     chain,
-                    timeout: None,
+                    timeout: timeout.or(chain_config.timeout),
                 })?,
                 funds: fund_bridge_position,
             }));
 
-            POSITIONS.remove(deps.storage, sender);
+            POSITIONS.remove(deps.storage, sender.clone());
 
-            Ok(Response::new().add_messages(msgs))
+            Ok(Response::new()
+                .add_messages(msgs)
+                .add_events(interest_event)
+                .add_events(events))
         }
     }
 }
 
-fn run_withdraw(
+fn run_approve_operator(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    operator: String,
+    allowances: Option<Vec<Coin>>,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+
+    let expires = expires.unwrap_or_default();
+
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::ApprovalExpired {});
+    }
+
+    APPROVALS.save(
+        deps.storage,
+        (user.clone(), operator.clone()),
+        &Approval {
+            allowances,
+            expires,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "approve_operator")
+        .add_attribute("user", user)
+        .add_attribute("operator", operator)
+        .add_attribute("expires", expires.to_string()))
+}
+
+fn run_revoke_operator(
+    deps: DepsMut,
+    user: Addr,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+
+    if !APPROVALS.has(deps.storage, (user.clone(), operator.clone())) {
+        return Err(ContractError::OperatorNotApproved {});
+    }
+
+    APPROVALS.remove(deps.storage, (user.clone(), operator.clone()));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_operator")
+        .add_attribute("user", user)
+        .add_attribute("operator", operator))
+}
+
+fn run_set_remote_addr(
     deps: DepsMut,
     user: Addr,
+    chain: String,
+    remote_addr: Option<String>,
+) -> Result<Response, ContractError> {
+    match &remote_addr {
+        Some(remote_addr) => {
+            REMOTE_ADDRS.save(deps.storage, (user.clone(), chain.clone()), remote_addr)?
+        }
+        None => REMOTE_ADDRS.remove(deps.storage, (user.clone(), chain.clone())),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_remote_addr")
+        .add_attribute("user", user)
+        .add_attribute("chain", chain)
+        .add_attribute("remote_addr", remote_addr.unwrap_or_default()))
+}
+
+fn run_withdraw(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
     denom: String,
     amount: Option<Uint128>,
+    on_behalf_of: Option<String>,
 ) -> Result<Response, ContractError> {
+    let user = resolve_user(&deps, &env, &sender, on_behalf_of)?;
+
+    let interest_event = accrue_interest(deps.storage, &env, &user)?;
+
+    if user != sender {
+        let amount = match amount {
+            Some(amount) => amount,
+            None => load_position(deps.storage, user.clone())?
+                .collaterals
+                .get(&denom)
+                .copied()
+                .ok_or(ContractError::CollateralNotFound {})?,
+        };
+
+        spend_allowance(deps.storage, &user, &sender, &denom, amount)?;
+    }
+
     let withdraw_amount = user_withdraw(deps.storage, &user, amount, denom.clone())?;
 
-    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: denom.clone(),
-        msg: to_binary(&Cw20ExecuteMsg::Transfer {
-            recipient: user.to_string(),
-            amount: withdraw_amount,
-        })?,
-        funds: vec![],
-    });
+    // Withdrawing lowers the borrow limit of the position
+    check_borrow_limit(deps.as_ref(), &load_position(deps.storage, user.clone())?)?;
+
+    // The collateral goes to the owner of the position, also when withdrawn by an operator
+    let msg = transfer_msg(&deps.as_ref(), &denom, withdraw_amount, &user)?;
 
     Ok(Response::new()
         .add_message(msg)
-        .add_attribute("withdraw_denom", denom)
-        .add_attribute("withdraw_amount", withdraw_amount))
+        .add_events(interest_event)
+        .add_event(operator_attribute(
+            market_event("withdraw", &user, denom, withdraw_amount),
+            &user,
+            &sender,
+        )))
 }
 
 fn run_increase_loan(
     deps: DepsMut,
-    user: Addr,
+    env: Env,
+    sender: Addr,
     amount: Uint128,
+    on_behalf_of: Option<String>,
 ) -> Result<Response, ContractError> {
+    let user = resolve_user(&deps, &env, &sender, on_behalf_of)?;
+
+    let interest_event = accrue_interest(deps.storage, &env, &user)?;
+
+    let mut position = load_position(deps.storage, user.clone())?;
+    position.loan = position.loan.checked_add(amount)?;
+
+    check_category(deps.storage, &position)?;
+    check_borrow_limit(deps.as_ref(), &position)?;
+
+    spend_allowance(deps.storage, &user, &sender, LOAN_DENOM, amount)?;
+
     user_increase_loan(deps.storage, &user, amount)?;
 
     Ok(Response::new()
-        .add_attribute("action", "loan_increased")
-        .add_attribute("user", user.to_string())
-        .add_attribute("amount", amount.to_string()))
+        .add_events(interest_event)
+        .add_event(operator_attribute(
+            market_event("borrow", &user, LOAN_DENOM, amount),
+            &user,
+            &sender,
+        )))
 }
 
 fn run_repay_loan(
     deps: DepsMut,
-    user: Addr,
+    env: Env,
+    sender: Addr,
     amount: Option<Uint128>,
+    on_behalf_of: Option<String>,
 ) -> Result<Response, ContractError> {
-    user_decrease_loan(deps.storage, &user, amount)?;
+    let user = resolve_user(&deps, &env, &sender, on_behalf_of)?;
+
+    let interest_event = accrue_interest(deps.storage, &env, &user)?;
+
+    let amount = match amount {
+        Some(amount) => amount,
+        None => load_position(deps.storage, user.clone())?.loan,
+    };
+
+    spend_allowance(deps.storage, &user, &sender, LOAN_DENOM, amount)?;
+
+    user_decrease_loan(deps.storage, &user, Some(amount))?;
 
     Ok(Response::new()
-        .add_attribute("action", "loan_increased")
-        .add_attribute("user", user.to_string()))
+        .add_events(interest_event)
+        .add_event(operator_attribute(
+            market_event("repay", &user, LOAN_DENOM, amount),
+            &user,
+            &sender,
+        )))
 }
 
 fn run_gate_revert_request(
     deps: DepsMut,
+    env: Env,
     gate: Addr,
     request: GateRequest,
 ) -> Result<Response, ContractError> {
@@ -350,16 +1076,38 @@ fn run_gate_revert_request(
 
         let user_addr = deps.api.addr_validate(bridge_msg.sender.as_str())?;
 
+        let interest_event = accrue_interest(deps.storage, &env, &user_addr)?;
+
         user_increase_loan(deps.storage, &user_addr, bridge_msg.src_position.loan)?;
 
-        for (denom, amount) in bridge_msg.src_position.collaterals {
+        for (denom, amount) in bridge_msg.src_position.collaterals.clone() {
             user_deposit(deps.storage, &user_addr, amount, denom)?;
         }
 
+        // The revert can't fail, the category is restored only if the user has not opted into another one
+        POSITIONS.update(
+            deps.storage,
+            user_addr.clone(),
+            |position| -> StdResult<Position> {
+                let mut position = position.ok_or(StdError::not_found("Position"))?;
+
+                position.category = position
+                    .category
+                    .or(bridge_msg.src_position.category.clone());
+
+                Ok(position)
+            },
+        )?;
+
         Ok(Response::new()
-            .add_attribute("action", "bridge_reverted")
-            .add_attribute("sender", bridge_msg.sender)
-            .add_attribute("receiver", user_addr.to_string()))
+            .add_events(interest_event)
+            .add_events(bridge_events(
+                "bridge_revert",
+                &user_addr,
+                &bridge_msg.src_position,
+                BridgeChain::Dest(&bridge_msg.chain),
+                bridge_msg.bridge_id,
+            )))
     } else {
         Err(ContractError::Std(StdError::generic_err(
             "Request not handled".to_string(),
@@ -369,36 +1117,190 @@ fn run_gate_revert_request(
 
 fn run_gate_receive_msg(
     deps: DepsMut,
+    env: Env,
     gate: Addr,
-    _remote_contract: String,
+    remote_contract: String,
     msg: Binary,
 ) -> Result<Response, ContractError> {
     onlygate(deps.storage, &gate)?;
 
+    let chain = find_chain(deps.storage, &remote_contract)?;
+
     let bridge_msg: BridgeMsgInfo = from_binary(&msg)?;
 
     let user_addr = deps.api.addr_validate(bridge_msg.receiver.as_str())?;
 
+    let interest_event = accrue_interest(deps.storage, &env, &user_addr)?;
+
     user_increase_loan(deps.storage, &user_addr, bridge_msg.dest_position.loan)?;
 
-    for (denom, amount) in bridge_msg.dest_position.collaterals {
+    for (denom, amount) in bridge_msg.dest_position.collaterals.clone() {
         user_deposit(deps.storage, &user_addr, amount, denom)?;
     }
 
+    // If the received position doesn't fit the category, the bridge is reverted
+    let position = user_merge_category(
+        deps.storage,
+        &user_addr,
+        bridge_msg.dest_position.category.clone(),
+    )?;
+
+    check_category(deps.storage, &position)?;
+
     Ok(Response::new()
-        .add_attribute("action", "bridge_received")
-        .add_attribute("sender", bridge_msg.sender)
-        .add_attribute("receiver", user_addr.to_string()))
+        .add_events(interest_event)
+        .add_events(bridge_events(
+            "bridge_in",
+            &user_addr,
+            &bridge_msg.dest_position,
+            BridgeChain::Src(&chain),
+            bridge_msg.bridge_id,
+        )))
+}
+
+// --- REPLY ---
+
+/// Check that the flash loan has been repaid with the fee, and move the fee to the reserves.
+fn reply_flash_loan(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let flash_loan = FLASH_LOAN.load(deps.storage)?;
+
+    let balance = query_balance(deps.as_ref(), &flash_loan.denom, &env.contract.address)?;
+
+    if balance < flash_loan.expected_balance {
+        return Err(ContractError::FlashLoanNotRepaid {
+            expected: flash_loan.expected_balance,
+            found: balance,
+        });
+    }
+
+    FLASH_LOAN.remove(deps.storage);
+
+    RESERVES.update(
+        deps.storage,
+        flash_loan.denom.clone(),
+        |reserve| -> StdResult<Uint128> {
+            Ok(reserve.unwrap_or_default().checked_add(flash_loan.fee)?)
+        },
+    )?;
+
+    Ok(Response::new().add_event(
+        market_event(
+            "flash_loan_repaid",
+            &flash_loan.receiver,
+            flash_loan.denom,
+            flash_loan.amount,
+        )
+        .add_attribute("fee", flash_loan.fee),
+    ))
 }
 
 // --- QUERIES ---
 
-fn qy_position(deps: Deps, user: Addr) -> StdResult<Position> {
-    let position = POSITIONS.load(deps.storage, user).unwrap();
+fn qy_position(deps: Deps, env: Env, user: Addr) -> StdResult<Position> {
+    let mut position = POSITIONS.load(deps.storage, user.clone())?;
+
+    position.loan = accrued_loan(deps.storage, &env, &user, position.loan)?;
 
     Ok(position)
 }
 
+fn qy_health(deps: Deps, env: Env, user: Addr) -> Result<HealthResponse, ContractError> {
+    position_health(deps, &load_accrued_position(deps.storage, &env, user)?)
+}
+
+fn qy_simulate_borrow(
+    deps: Deps,
+    env: Env,
+    user: Addr,
+    amount: Uint128,
+) -> Result<HealthResponse, ContractError> {
+    let mut position = load_accrued_position(deps.storage, &env, user)?;
+
+    position.loan = position.loan.checked_add(amount)?;
+
+    position_health(deps, &position)
+}
+
+fn qy_simulate_withdraw(
+    deps: Deps,
+    env: Env,
+    user: Addr,
+    denom: String,
+    amount: Option<Uint128>,
+) -> Result<HealthResponse, ContractError> {
+    let mut position = load_accrued_position(deps.storage, &env, user)?;
+
+    let coll_amount = position
+        .collaterals
+        .remove(&denom)
+        .ok_or_else(|| StdError::generic_err("Collateral not found"))?;
+
+    if let Some(amount) = amount {
+        position
+            .collaterals
+            .insert(denom, coll_amount.checked_sub(amount)?);
+    }
+
+    position_health(deps, &position)
+}
+
+fn qy_category(deps: Deps, name: String) -> StdResult<CollateralCategory> {
+    CATEGORIES.load(deps.storage, name)
+}
+
+fn qy_categories(deps: Deps) -> StdResult<Vec<(String, CollateralCategory)>> {
+    CATEGORIES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect()
+}
+
+fn qy_approval(deps: Deps, user: Addr, operator: Addr) -> StdResult<Approval> {
+    APPROVALS.load(deps.storage, (user, operator))
+}
+
+fn qy_approvals(deps: Deps, user: Addr) -> StdResult<Vec<(Addr, Approval)>> {
+    APPROVALS
+        .prefix(user)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect()
+}
+
+fn qy_remote_addr(deps: Deps, user: Addr, chain: String) -> StdResult<Option<String>> {
+    REMOTE_ADDRS.may_load(deps.storage, (user, chain))
+}
+
+fn qy_chain_config(deps: Deps, chain: String) -> StdResult<ChainConfig> {
+    Ok(CHAINS_CONFIG
+        .may_load(deps.storage, chain)?
+        .unwrap_or_default())
+}
+
+fn qy_bridge_fee(deps: Deps, chain: String, cw20_collaterals: u64) -> StdResult<BridgeFeeResponse> {
+    let chain_config = CHAINS_CONFIG
+        .may_load(deps.storage, chain)?
+        .unwrap_or_default();
+
+    Ok(BridgeFeeResponse {
+        requests: cw20_collaterals + 1,
+        fee: total_bridge_fee(&chain_config, cw20_collaterals),
+        protocol_fee: chain_config.bridge_fee,
+    })
+}
+
+fn qy_reserve(deps: Deps, denom: String) -> StdResult<Uint128> {
+    Ok(RESERVES.may_load(deps.storage, denom)?.unwrap_or_default())
+}
+
+fn qy_interest_config(deps: Deps) -> StdResult<InterestConfig> {
+    Ok(INTEREST_CONFIG.may_load(deps.storage)?.unwrap_or_default())
+}
+
+fn qy_remote_contracts(deps: Deps, chain: String) -> StdResult<Vec<String>> {
+    Ok(CHAINS_CONTRACT
+        .may_load(deps.storage, chain)?
+        .unwrap_or_default())
+}
+
 // --- FUNCTIONS ---
 
 fn onlyowner(storage: &dyn Storage, address: &Addr) -> Result<(), ContractError> {
@@ -415,6 +1317,462 @@ fn onlygate(storage: &dyn Storage, address: &Addr) -> Result<(), ContractError>
     Ok(())
 }
 
+/// Return the user whose position is managed by `sender`.
+/// If `on_behalf_of` is specified, `sender` has to be an operator approved by that user.
+fn resolve_user(
+    deps: &DepsMut,
+    env: &Env,
+    sender: &Addr,
+    on_behalf_of: Option<String>,
+) -> Result<Addr, ContractError> {
+    let user = match on_behalf_of {
+        Some(user) => deps.api.addr_validate(&user)?,
+        None => return Ok(sender.to_owned()),
+    };
+
+    let approval = APPROVALS
+        .may_load(deps.storage, (user.clone(), sender.to_owned()))?
+        .ok_or(ContractError::OperatorNotApproved {})?;
+
+    if approval.expires.is_expired(&env.block) {
+        return Err(ContractError::ApprovalExpired {});
+    }
+
+    Ok(user)
+}
+
+/// Deduct `amount` from the allowance of `denom` granted by `user` to `operator`.
+/// Nothing to do if the user is acting on its own position or the operator has no limits.
+fn spend_allowance(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    operator: &Addr,
+    denom: &str,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if user == operator {
+        return Ok(());
+    }
+
+    let key = (user.to_owned(), operator.to_owned());
+
+    let mut approval = APPROVALS.load(storage, key.clone())?;
+
+    if let Some(allowances) = &mut approval.allowances {
+        let allowance = allowances
+            .iter_mut()
+            .find(|coin| coin.denom == denom)
+            .filter(|coin| coin.amount >= amount)
+            .ok_or(ContractError::AllowanceExceeded {
+                denom: denom.to_string(),
+            })?;
+
+        allowance.amount -= amount;
+
+        APPROVALS.save(storage, key, &approval)?;
+    }
+
+    Ok(())
+}
+
+/// Add the `operator` attribute to `event` if the position of `user` has been changed by an operator.
+fn operator_attribute(event: Event, user: &Addr, operator: &Addr) -> Event {
+    if user == operator {
+        event
+    } else {
+        event.add_attribute("operator", operator)
+    }
+}
+
+/// Fail if the debt of `position` exceeds its borrow limit.
+fn check_borrow_limit(deps: Deps, position: &Position) -> Result<(), ContractError> {
+    if position.loan.is_zero() {
+        return Ok(());
+    }
+
+    let health = position_health(deps, position)?;
+
+    if health.debt_value > health.borrow_limit {
+        return Err(ContractError::BorrowLimitExceeded {
+            debt_value: health.debt_value,
+            borrow_limit: health.borrow_limit,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that a position in a category holds only collaterals of the category.
+fn check_category(storage: &dyn Storage, position: &Position) -> Result<(), ContractError> {
+    if let Some(name) = &position.category {
+        let category = CATEGORIES.may_load(storage, name.to_owned())?.ok_or(
+            ContractError::CategoryNotFound {
+                category: name.to_owned(),
+            },
+        )?;
+
+        position.is_in_category(name, &category)?;
+    }
+
+    Ok(())
+}
+
+/// Return the remote contract to use for `chain`.
+/// If `remote_contract` is not specified, the chain must have only one remote contract registered.
+fn load_remote_contract(
+    storage: &dyn Storage,
+    chain: &str,
+    remote_contract: Option<String>,
+) -> Result<String, ContractError> {
+    let contracts = CHAINS_CONTRACT
+        .may_load(storage, chain.to_string())?
+        .ok_or(ContractError::ChainNotRegistered {
+            chain: chain.to_string(),
+        })?;
+
+    match remote_contract {
+        Some(contract) => {
+            if !contracts.contains(&contract) {
+                return Err(ContractError::RemoteContractNotRegistered {
+                    contract,
+                    chain: chain.to_string(),
+                });
+            }
+            Ok(contract)
+        }
+        None => match contracts.as_slice() {
+            [contract] => Ok(contract.to_owned()),
+            _ => Err(ContractError::RemoteContractRequired {
+                chain: chain.to_string(),
+            }),
+        },
+    }
+}
+
+/// Return the chain where `remote_contract` is registered.
+fn find_chain(storage: &dyn Storage, remote_contract: &str) -> Result<String, ContractError> {
+    for item in CHAINS_CONTRACT.range(storage, None, None, Order::Ascending) {
+        let (chain, contracts) = item?;
+
+        if contracts.iter().any(|contract| contract == remote_contract) {
+            return Ok(chain);
+        }
+    }
+
+    Err(ContractError::Unauthorized {})
+}
+
+/// Build a `wasm-market` event. Every change of a position emit one event for every denom changed,
+/// the loan is reported with the `LOAN_DENOM` denom.
+fn market_event(action: &str, user: &Addr, denom: impl Into<String>, amount: Uint128) -> Event {
+    Event::new("market")
+        .add_attribute("action", action)
+        .add_attribute("user", user)
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+}
+
+/// Build the `wasm-market` events for a bridged `position`, sorted by denom.
+/// The remote chain is reported as `src_chain` if the position comes from it, as `dest_chain` if the position was sent to it.
+fn bridge_events(
+    action: &str,
+    user: &Addr,
+    position: &Position,
+    remote_chain: BridgeChain,
+    bridge_id: u64,
+) -> Vec<Event> {
+    let mut events: Vec<Event> = position
+        .collaterals
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .map(|(denom, amount)| market_event(action, user, denom, *amount))
+        .collect();
+
+    if !position.loan.is_zero() {
+        events.push(market_event(action, user, LOAN_DENOM, position.loan));
+    }
+
+    events
+        .into_iter()
+        .map(|event| {
+            let event = match &remote_chain {
+                BridgeChain::Src(chain) => event.add_attribute("src_chain", *chain),
+                BridgeChain::Dest(chain) => event.add_attribute("dest_chain", *chain),
+            };
+
+            event.add_attribute("bridge_id", bridge_id.to_string())
+        })
+        .collect()
+}
+
+/// Remote chain of a bridge, reported in the `wasm-market` events.
+enum BridgeChain<'a> {
+    /// The position comes from the chain.
+    Src(&'a str),
+    /// The position was sent to the chain.
+    Dest(&'a str),
+}
+
+/// Return the position of `user` with the interest accrued until `env`, without saving it.
+fn load_accrued_position(storage: &dyn Storage, env: &Env, user: Addr) -> StdResult<Position> {
+    let mut position = load_position(storage, user.clone())?;
+
+    position.loan = accrued_loan(storage, env, &user, position.loan)?;
+
+    Ok(position)
+}
+
+/// Load the position of `user`, returning an empty position if the user has never deposited.
+fn load_position(storage: &dyn Storage, user: Addr) -> StdResult<Position> {
+    Ok(POSITIONS.may_load(storage, user)?.unwrap_or(Position {
+        loan: Uint128::zero(),
+        collaterals: HashMap::new(),
+        category: None,
+    }))
+}
+
+/// Return the balance of `address` for `denom`, native coin or cw20.
+fn query_balance(deps: Deps, denom: &str, address: &Addr) -> StdResult<Uint128> {
+    if is_native(&deps, denom) {
+        Ok(deps.querier.query_balance(address, denom)?.amount)
+    } else {
+        let response: BalanceResponse = deps.querier.query_wasm_smart(
+            denom,
+            &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )?;
+
+        Ok(response.balance)
+    }
+}
+
+fn query_price(deps: Deps, oracle: &Addr, asset: String) -> StdResult<Decimal> {
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: oracle.to_string(),
+        msg: to_binary(&OracleQueryMsg::Price {
+            asset,
+            max_age: None,
+            selector: None,
+        })?,
+    }))
+}
+
+/// Compute the health of `position` using the prices of the registered oracle.
+/// If the position is in a category, the collaterals of the category use the category ltv and the others don't increase the borrow limit.
+fn position_health(deps: Deps, position: &Position) -> Result<HealthResponse, ContractError> {
+    let oracle = ORACLE.load(deps.storage)?;
+
+    let category = match &position.category {
+        Some(name) => Some(CATEGORIES.load(deps.storage, name.to_owned())?),
+        None => None,
+    };
+
+    let mut collateral_value = Uint128::zero();
+    let mut borrow_limit = Uint128::zero();
+    let mut liquidation_limit = Uint128::zero();
+
+    for (denom, amount) in &position.collaterals {
+        if let Some(config) = COLLATERALS_CONFIG.may_load(deps.storage, denom.to_owned())? {
+            let value = *amount * query_price(deps, &oracle, config.oracle_asset)?;
+
+            let (ltv, liquidation_threshold) = match &category {
+                Some(category) if category.collaterals.contains(denom) => {
+                    (category.ltv, category.liquidation_threshold)
+                }
+                Some(_) => (Decimal::zero(), Decimal::zero()),
+                None => (config.ltv, config.ltv),
+            };
+
+            collateral_value = collateral_value.checked_add(value)?;
+            borrow_limit = borrow_limit.checked_add(value * ltv)?;
+            liquidation_limit = liquidation_limit.checked_add(value * liquidation_threshold)?;
+        }
+    }
+
+    let loan_price = query_price(deps, &oracle, LOAN_ASSET.load(deps.storage)?)?;
+
+    if loan_price.is_zero() {
+        return Err(StdError::generic_err("Loan price is zero").into());
+    }
+
+    let debt_value = position.loan * loan_price;
+
+    let ltv = if collateral_value.is_zero() {
+        None
+    } else {
+        Some(Decimal::from_ratio(debt_value, collateral_value))
+    };
+
+    // A tiny loan price can overflow the amount
+    let max_borrowable = borrow_limit
+        .saturating_sub(debt_value)
+        .checked_multiply_ratio(loan_price.denominator(), loan_price.numerator())?;
+
+    Ok(HealthResponse {
+        collateral_value,
+        borrow_limit,
+        liquidation_limit,
+        debt_value,
+        ltv,
+        max_borrowable,
+    })
+}
+
+/// Split the fee received with a `BridgePosition` between the `gate` requests:
+/// one for every cw20 collateral plus the one sent by the market.
+///
+/// If a `fee_per_request` is registered for the chain, every cw20 request receive exactly that fee and the market request receive the rest.
+/// Otherwise the fee is equally divided and the market request receive also the remainder of the division.
+pub(crate) fn split_bridge_fee(
+    fee: Option<Coin>,
+    cw20_collaterals: u64,
+    chain_config: &ChainConfig,
+) -> Result<BridgeFeeSplit, ContractError> {
+    let requests = Uint128::from(cw20_collaterals) + Uint128::one();
+
+    let (fee, amount_per_cw20) = match (fee, expected_bridge_fee(chain_config, cw20_collaterals)) {
+        (None, None) => return Ok(BridgeFeeSplit::default()),
+        (fee, Some(expected)) => match fee {
+            Some(fee) if fee.denom == expected.denom && fee.amount >= expected.amount => {
+                (fee, expected.amount / requests)
+            }
+            _ => return Err(ContractError::InsufficientFee { expected }),
+        },
+        (Some(fee), None) => {
+            // Every request has to receive at least one unit
+            if fee.amount < requests {
+                return Err(ContractError::InsufficientFee {
+                    expected: Coin {
+                        denom: fee.denom,
+                        amount: requests,
+                    },
+                });
+            }
+
+            let amount_per_cw20 = fee.amount / requests;
+            (fee, amount_per_cw20)
+        }
+    };
+
+    let market_amount = fee
+        .amount
+        .checked_sub(amount_per_cw20.checked_mul(Uint128::from(cw20_collaterals))?)?;
+
+    let to_coin = |amount: Uint128| {
+        (!amount.is_zero()).then(|| Coin {
+            denom: fee.denom.clone(),
+            amount,
+        })
+    };
+
+    Ok(BridgeFeeSplit {
+        per_cw20: to_coin(amount_per_cw20),
+        market: to_coin(market_amount),
+    })
+}
+
+/// Return the total fee expected to bridge a position with `cw20_collaterals` cw20 collaterals:
+/// every cw20 collateral send its own `gate` request, plus the request sent by the market.
+fn expected_bridge_fee(chain_config: &ChainConfig, cw20_collaterals: u64) -> Option<Coin> {
+    chain_config.fee_per_request.as_ref().map(|fee| Coin {
+        denom: fee.denom.clone(),
+        amount: fee.amount * Uint128::from(cw20_collaterals + 1),
+    })
+}
+
+/// Return the total fee expected to bridge a position: the `gate` fees plus the fee kept by the protocol.
+fn total_bridge_fee(chain_config: &ChainConfig, cw20_collaterals: u64) -> Option<Coin> {
+    match (
+        expected_bridge_fee(chain_config, cw20_collaterals),
+        &chain_config.bridge_fee,
+    ) {
+        (Some(gate_fee), Some(bridge_fee)) => Some(Coin {
+            denom: gate_fee.denom,
+            amount: gate_fee.amount + bridge_fee.amount,
+        }),
+        (gate_fee, bridge_fee) => gate_fee.or(bridge_fee.clone()),
+    }
+}
+
+/// Move the `bridge_fee` registered for the chain from the fee received with a `BridgePosition` to the reserves.
+/// Return the fee left for the `gate` requests.
+fn take_bridge_fee(
+    storage: &mut dyn Storage,
+    fee: Option<Coin>,
+    cw20_collaterals: u64,
+    chain_config: &ChainConfig,
+) -> Result<Option<Coin>, ContractError> {
+    let bridge_fee = match &chain_config.bridge_fee {
+        Some(bridge_fee) => bridge_fee,
+        None => return Ok(fee),
+    };
+
+    // Same denom of bridge_fee, checked on SetChainConfig
+    let expected = total_bridge_fee(chain_config, cw20_collaterals).unwrap_or(bridge_fee.clone());
+
+    match fee {
+        Some(fee) if fee.denom == expected.denom && fee.amount >= expected.amount => {
+            RESERVES.update(storage, fee.denom.clone(), |reserve| -> StdResult<_> {
+                Ok(reserve.unwrap_or_default().checked_add(bridge_fee.amount)?)
+            })?;
+
+            let amount = fee.amount - bridge_fee.amount;
+
+            Ok((!amount.is_zero()).then_some(Coin {
+                denom: fee.denom,
+                amount,
+            }))
+        }
+        _ => Err(ContractError::InsufficientFee { expected }),
+    }
+}
+
+/// Build the msg to send `amount` of `denom`, native coin or cw20, to `recipient`.
+fn transfer_msg(
+    deps: &Deps,
+    denom: &str,
+    amount: Uint128,
+    recipient: &Addr,
+) -> StdResult<CosmosMsg> {
+    if is_native(deps, denom) {
+        Ok(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: denom.to_string(),
+                amount,
+            }],
+        }))
+    } else {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: denom.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }))
+    }
+}
+
+/// Build the `SetPermission` msg for `gate`, granting the permission to all `addresses`.
+/// An empty list revoke the permission to any remote contract.
+fn gate_set_permission_msg(
+    storage: &dyn Storage,
+    addresses: Vec<String>,
+    chain: String,
+) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: GATE.load(storage)?.to_string(),
+        msg: to_binary(&GateExecuteMsg::SetPermission {
+            permission: Permission::Permissioned { addresses },
+            chain,
+        })?,
+        funds: vec![],
+    }))
+}
+
 fn onecoin(coins: Vec<Coin>) -> Result<Option<Coin>, ContractError> {
     if coins.len() == 1 {
         return Ok(Some(coins.first().unwrap().to_owned()));
@@ -434,7 +1792,12 @@ fn user_deposit(
     user: &Addr,
     amount: Uint128,
     denom: String,
-) -> Result<Response, ContractError> {
+) -> Result<(), ContractError> {
+    // The funds of a flash loan can't be deposited as collateral
+    if FLASH_LOAN.exists(storage) {
+        return Err(ContractError::FlashLoanInProgress {});
+    }
+
     match POSITIONS.load(storage, user.to_owned()) {
         Ok(position) => {
             let coll_amount = position.collaterals.get(&denom);
@@ -481,15 +1844,17 @@ fn user_deposit(
                 &Position {
                     loan: Uint128::zero(),
                     collaterals,
+                    category: None,
                 },
             )?;
         }
     }
 
-    Ok(Response::new()
-        .add_attribute("action", "deposit")
-        .add_attribute("denom", denom)
-        .add_attribute("amount", amount.to_string()))
+    TOTAL_DEPOSITS.update(storage, denom, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default().checked_add(amount)?)
+    })?;
+
+    Ok(())
 }
 
 fn user_withdraw(
@@ -513,12 +1878,16 @@ fn user_withdraw(
                             |position| -> Result<Position, ContractError> {
                                 let mut position: Position = position.unwrap();
 
-                                position.collaterals.insert(token_contract, new_amount);
+                                position
+                                    .collaterals
+                                    .insert(token_contract.clone(), new_amount);
                                 Ok(position)
                             },
                         )?;
 
-                        Ok(coll_amount.to_owned())
+                        decrease_total_deposits(storage, &token_contract, amount)?;
+
+                        Ok(amount)
                     }
                     None => {
                         POSITIONS.update(
@@ -533,6 +1902,8 @@ fn user_withdraw(
                             },
                         )?;
 
+                        decrease_total_deposits(storage, &token_contract, *coll_amount)?;
+
                         Ok(coll_amount.to_owned())
                     }
                 },
@@ -543,6 +1914,100 @@ fn user_withdraw(
     }
 }
 
+/// Return `BORROW_INDEX` updated with the interest accrued until `env`.
+fn current_borrow_index(storage: &dyn Storage, env: &Env) -> StdResult<BorrowIndex> {
+    let config = INTEREST_CONFIG.may_load(storage)?.unwrap_or_default();
+
+    let borrow_index = BORROW_INDEX.may_load(storage)?.unwrap_or(BorrowIndex {
+        index: Decimal::one(),
+        last_update: env.block.time,
+    });
+
+    let elapsed = env
+        .block
+        .time
+        .seconds()
+        .saturating_sub(borrow_index.last_update.seconds());
+
+    let interest = config
+        .borrow_rate
+        .checked_mul(Decimal::from_ratio(elapsed, SECONDS_PER_YEAR))?;
+
+    Ok(BorrowIndex {
+        index: borrow_index
+            .index
+            .checked_mul(Decimal::one().checked_add(interest)?)?,
+        last_update: env.block.time,
+    })
+}
+
+/// Return the `loan` of `user` plus the interest accrued since its last accrual.
+fn accrued_loan(
+    storage: &dyn Storage,
+    env: &Env,
+    user: &Addr,
+    loan: Uint128,
+) -> StdResult<Uint128> {
+    let index = current_borrow_index(storage, env)?.index;
+
+    let position_index = POSITION_BORROW_INDEX
+        .may_load(storage, user.to_owned())?
+        .unwrap_or(index);
+
+    loan.checked_multiply_ratio(index.atomics(), position_index.atomics())
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// Add to the loan of `user` the interest accrued since its last accrual, the reserve factor share goes to the `LOAN_DENOM` reserves.
+/// Return the `wasm-market` event of the accrual if there is interest.
+fn accrue_interest(storage: &mut dyn Storage, env: &Env, user: &Addr) -> StdResult<Option<Event>> {
+    let mut event = None;
+
+    if let Some(mut position) = POSITIONS.may_load(storage, user.to_owned())? {
+        let loan = accrued_loan(storage, env, user, position.loan)?;
+        let interest = loan - position.loan;
+
+        if !interest.is_zero() {
+            let reserve = interest * INTEREST_CONFIG.load(storage)?.reserve_factor;
+
+            RESERVES.update(
+                storage,
+                LOAN_DENOM.to_string(),
+                |reserves| -> StdResult<_> {
+                    Ok(reserves.unwrap_or_default().checked_add(reserve)?)
+                },
+            )?;
+
+            position.loan = loan;
+            POSITIONS.save(storage, user.to_owned(), &position)?;
+
+            event = Some(
+                market_event("accrue_interest", user, LOAN_DENOM, interest)
+                    .add_attribute("reserve", reserve),
+            );
+        }
+    }
+
+    let borrow_index = current_borrow_index(storage, env)?;
+
+    POSITION_BORROW_INDEX.save(storage, user.to_owned(), &borrow_index.index)?;
+    BORROW_INDEX.save(storage, &borrow_index)?;
+
+    Ok(event)
+}
+
+fn decrease_total_deposits(
+    storage: &mut dyn Storage,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    TOTAL_DEPOSITS.update(storage, denom.to_string(), |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default().saturating_sub(amount))
+    })?;
+
+    Ok(())
+}
+
 fn user_increase_loan(
     storage: &mut dyn Storage,
     user: &Addr,
@@ -569,6 +2034,7 @@ fn user_increase_loan(
                 &Position {
                     loan: amount,
                     collaterals: HashMap::new(),
+                    category: None,
                 },
             )?;
         }
@@ -581,7 +2047,7 @@ fn user_decrease_loan(
     storage: &mut dyn Storage,
     user: &Addr,
     amount: Option<Uint128>,
-) -> Result<(), ContractError> {
+) -> Result<Uint128, ContractError> {
     match POSITIONS.load(storage, user.to_owned()) {
         Ok(pos) => {
             let amount = amount.unwrap_or(pos.loan);
@@ -596,9 +2062,35 @@ fn user_decrease_loan(
                     Ok(position)
                 },
             )?;
+
+            Ok(amount)
         }
-        Err(_) => return Err(ContractError::UserNotFound {}),
+        Err(_) => Err(ContractError::UserNotFound {}),
     }
+}
 
-    Ok(())
+/// Move the position of `user` into `category`.
+/// Fail if the position is alredy in a different category.
+fn user_merge_category(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    category: Option<String>,
+) -> Result<Position, ContractError> {
+    let mut position = POSITIONS.load(storage, user.to_owned())?;
+
+    if let Some(category) = category {
+        match &position.category {
+            Some(current) if *current != category => {
+                return Err(ContractError::CategoryMismatch {
+                    category: current.to_owned(),
+                })
+            }
+            _ => {
+                position.category = Some(category);
+                POSITIONS.save(storage, user.to_owned(), &position)?;
+            }
+        }
+    }
+
+    Ok(position)
 }
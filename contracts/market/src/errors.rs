@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{CheckedMultiplyRatioError, Coin, Decimal, OverflowError, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,6 +6,12 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    CheckedMultiplyRatio(#[from] CheckedMultiplyRatioError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
@@ -14,4 +20,87 @@ pub enum ContractError {
 
     #[error("Collateral not found")]
     CollateralNotFound {},
+
+    #[error("Invalid ltv {ltv}, it has to be lower or equal to 1")]
+    InvalidLtv { ltv: Decimal },
+
+    #[error("Invalid liquidation threshold {liquidation_threshold}, it has to be between the ltv {ltv} and 1")]
+    InvalidLiquidationThreshold {
+        ltv: Decimal,
+        liquidation_threshold: Decimal,
+    },
+
+    #[error("Category {category} not found")]
+    CategoryNotFound { category: String },
+
+    #[error("Collateral {denom} not allowed in category {category}")]
+    CollateralNotInCategory { denom: String, category: String },
+
+    #[error("Position alredy in category {category}")]
+    CategoryMismatch { category: String },
+
+    #[error("Debt value {debt_value} exceed the borrow limit {borrow_limit}")]
+    BorrowLimitExceeded {
+        debt_value: Uint128,
+        borrow_limit: Uint128,
+    },
+
+    #[error("Insufficient liquidity, available {available}")]
+    InsufficientLiquidity { available: Uint128 },
+
+    #[error("Flash loan in progress")]
+    FlashLoanInProgress {},
+
+    #[error("Flash loan not repaid, expected balance {expected}, found {found}")]
+    FlashLoanNotRepaid { expected: Uint128, found: Uint128 },
+
+    #[error("Insufficient reserves, available {available}")]
+    InsufficientReserves { available: Uint128 },
+
+    #[error("Invalid reserve factor {reserve_factor}, it has to be lower or equal to 1")]
+    InvalidReserveFactor { reserve_factor: Decimal },
+
+    #[error("The loan reserves are not withdrawable, they are repaid with the loans")]
+    LoanReservesNotWithdrawable {},
+
+    #[error("The bridge fee and the fee per request must have the same denom")]
+    FeeDenomMismatch {},
+
+    #[error("Operator not approved")]
+    OperatorNotApproved {},
+
+    #[error("Approval expired")]
+    ApprovalExpired {},
+
+    #[error("Allowance exceeded for {denom}")]
+    AllowanceExceeded { denom: String },
+
+    #[error("Operators with limited allowances can't bridge the position")]
+    OperatorAllowanceLimited {},
+
+    #[error("Operators can only bridge to the address registered by the owner on {chain}")]
+    RemoteAddrNotRegistered { chain: String },
+
+    #[error("Chain {chain} not registered")]
+    ChainNotRegistered { chain: String },
+
+    #[error("Remote contract {contract} alredy registered for chain {chain}")]
+    RemoteContractAlredyRegistered { contract: String, chain: String },
+
+    #[error("Remote contract {contract} not registered for chain {chain}")]
+    RemoteContractNotRegistered { contract: String, chain: String },
+
+    #[error("Native info required to bridge a native collateral")]
+    NativeInfoRequired {},
+
+    #[error("Native info received but the position has no native collateral")]
+    NativeCollateralNotFound {},
+
+    #[error("Insufficient fee, expected {expected}")]
+    InsufficientFee { expected: Coin },
+
+    #[error(
+        "Multiple remote contracts registered for chain {chain}, remote_contract must be specified"
+    )]
+    RemoteContractRequired { chain: String },
 }
@@ -5,3 +5,6 @@ pub mod state;
 
 #[cfg(test)]
 pub mod test;
+
+#[cfg(test)]
+pub mod multitest;
@@ -1,10 +1,16 @@
 use std::collections::HashMap;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Deps, StdError, StdResult, Uint128};
-use cw20::Cw20ReceiveMsg;
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Deps, StdError, StdResult, Uint128};
+use cw20::{Cw20ReceiveMsg, Expiration};
+use enum_repr::EnumRepr;
 use gate_pkg::{GateMsg, PacketPath};
 
+use crate::{
+    errors::ContractError,
+    state::{Approval, ChainConfig, CollateralCategory, InterestConfig},
+};
+
 #[cw_serde]
 pub struct InstantiateMsg {}
 
@@ -14,19 +20,104 @@ pub enum ExecuteMsg {
     RegisterGate {
         contract: Addr,
     },
+    /// Register the oracle used to price the positions, and the oracle asset used to price the loan.
+    RegisterOracle {
+        contract: Addr,
+        loan_asset: String,
+    },
+    /// Register (or update) the oracle asset and the ltv of a collateral.
+    RegisterCollateral {
+        denom: String,
+        oracle_asset: String,
+        ltv: Decimal,
+    },
+    /// Register (or update) a collateral category (e-mode), with its own ltv and liquidation threshold.
+    RegisterCategory {
+        name: String,
+        collaterals: Vec<String>,
+        ltv: Decimal,
+        liquidation_threshold: Decimal,
+    },
+    /// Opt the position of the sender into a collateral category, or out of it if `None`.
+    /// All the collaterals of the position have to belong to the category.
+    SetPositionCategory {
+        category: Option<String>,
+    },
+    /// Add a remote contract to the list of contracts allowed to send msgs from `chain`.
     GateSetPermission {
         contract: String,
         chain: String,
     },
+    /// Remove a remote contract from the list of contracts allowed to send msgs from `chain`.
+    /// The chain is removed with its last remote contract, as with `RemoveChain`.
+    GateRemovePermission {
+        contract: String,
+        chain: String,
+    },
+    /// Remove all remote contracts registered for `chain`, revoking the permission on `gate`.
+    RemoveChain {
+        chain: String,
+    },
+    /// Set the default timeout and the fee expected for every `gate` request sent to `chain`,
+    /// plus the fee kept by the protocol for every position bridged.
+    SetChainConfig {
+        chain: String,
+        timeout: Option<u64>,
+        fee_per_request: Option<Coin>,
+        bridge_fee: Option<Coin>,
+    },
+    /// Send `amount` of the `denom` reserves to `recipient`.
+    WithdrawReserves {
+        denom: String,
+        amount: Uint128,
+        recipient: String,
+    },
+    /// Set the yearly interest rate of the loans and the share of the interest kept by the protocol.
+    SetInterestConfig {
+        borrow_rate: Decimal,
+        reserve_factor: Decimal,
+    },
+    /// Set the fee paid on the amount borrowed with a `FlashLoan`.
+    SetFlashLoanFee {
+        fee: Decimal,
+    },
+    /// Send `amount` of `denom` to the sender, that is called back with `FlashLoanReceiverMsg::FlashLoanCallback`.
+    /// At the end of the callback the market must have received back `amount` plus the fee.
+    FlashLoan {
+        denom: String,
+        amount: Uint128,
+        callback: Binary,
+    },
+    /// Approve `operator` to borrow, repay, withdraw and bridge the position of the sender.
+    /// Approving an alredy approved operator replace the previous approval.
+    ApproveOperator {
+        operator: String,
+        /// Max amount of every denom the operator can withdraw, `LOAN_DENOM` for the loan it can borrow and repay.
+        /// `None` if the operator has no limits, only operators without limits can bridge the position.
+        allowances: Option<Vec<Coin>>,
+        expires: Option<Expiration>,
+    },
+    RevokeOperator {
+        operator: String,
+    },
+    /// Register the address of the sender on `chain`, where the operators can bridge the position. `None` removes it.
+    SetRemoteAddr {
+        chain: String,
+        remote_addr: Option<String>,
+    },
+    /// Withdraw from the position of `on_behalf_of` if specified, the collateral is always sent to the owner of the position.
     Withdraw {
         denom: String,
         amount: Option<Uint128>,
+        on_behalf_of: Option<String>,
     },
     IncreaseLoan {
         amount: Uint128,
+        on_behalf_of: Option<String>,
     },
     RepayLoan {
         amount: Option<Uint128>,
+        on_behalf_of: Option<String>,
     },
     // Gate msg receive implementation
     ReceiveGateMsg(GateMsg),
@@ -39,11 +130,67 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     #[returns(Position)]
     Position { user: Addr },
+    #[returns(HealthResponse)]
+    Health { user: Addr },
+    /// Return the health of the position after borrowing `amount`.
+    #[returns(HealthResponse)]
+    SimulateBorrow { user: Addr, amount: Uint128 },
+    /// Return the health of the position after withdrawing `amount` (or all if not specified) of `denom`.
+    #[returns(HealthResponse)]
+    SimulateWithdraw {
+        user: Addr,
+        denom: String,
+        amount: Option<Uint128>,
+    },
+    #[returns(CollateralCategory)]
+    Category { name: String },
+    #[returns(Vec<(String, CollateralCategory)>)]
+    Categories {},
+    /// Return the amount of `denom` owned by the protocol, `LOAN_DENOM` for the share of the interest.
+    #[returns(Uint128)]
+    Reserve { denom: String },
+    #[returns(InterestConfig)]
+    InterestConfig {},
+    #[returns(Approval)]
+    Approval { user: Addr, operator: Addr },
+    #[returns(Vec<(Addr, Approval)>)]
+    Approvals { user: Addr },
+    /// Return the address of `user` on `chain` registered with `SetRemoteAddr`.
+    #[returns(Option<String>)]
+    RemoteAddr { user: Addr, chain: String },
+    #[returns(Vec<String>)]
+    RemoteContracts { chain: String },
+    #[returns(ChainConfig)]
+    ChainConfig { chain: String },
+    /// Return the fee expected to bridge a position with `cw20_collaterals` cw20 collaterals (plus an optional native one) to `chain`.
+    #[returns(BridgeFeeResponse)]
+    BridgeFee {
+        chain: String,
+        cw20_collaterals: u64,
+    },
 }
 
 #[cw_serde]
 pub struct MigrateMsg {}
 
+/// Msg executed on the receiver of a `FlashLoan`.
+#[cw_serde]
+pub enum FlashLoanReceiverMsg {
+    FlashLoanCallback {
+        denom: String,
+        amount: Uint128,
+        /// Fee to send back to the market together with `amount`.
+        fee: Uint128,
+        /// `callback` specified in the `FlashLoan`.
+        msg: Binary,
+    },
+}
+
+#[EnumRepr(type = "u64")]
+pub enum MsgReplyID {
+    FlashLoan = 1,
+}
+
 #[cw_serde]
 pub enum Cw20MsgType {
     Deposit {},
@@ -53,6 +200,9 @@ pub enum Cw20MsgType {
 pub struct Position {
     pub loan: Uint128,
     pub collaterals: HashMap<String, Uint128>,
+    /// Collateral category the position opted into. Positions in a category can only borrow and be bridged
+    /// holding collaterals of the category.
+    pub category: Option<String>,
 }
 
 impl Position {
@@ -70,6 +220,41 @@ impl Position {
 
         Ok(())
     }
+
+    /// Check that the position holds only collaterals of `category`.
+    pub fn is_in_category(
+        &self,
+        category_name: &str,
+        category: &CollateralCategory,
+    ) -> Result<(), ContractError> {
+        for denom in self.collaterals.keys() {
+            if !category.collaterals.contains(denom) {
+                return Err(ContractError::CollateralNotInCategory {
+                    denom: denom.to_owned(),
+                    category: category_name.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cw_serde]
+pub struct HealthResponse {
+    /// Value of the collaterals, priced by the oracle. Collaterals not registered are not considered.
+    pub collateral_value: Uint128,
+    /// Max value of the debt allowed by the collaterals ltv.
+    pub borrow_limit: Uint128,
+    /// Value of the debt over which the position can be liquidated, from the liquidation threshold of the category.
+    /// Equal to `borrow_limit` for the positions without a category.
+    pub liquidation_limit: Uint128,
+    /// Value of the loan, priced by the oracle.
+    pub debt_value: Uint128,
+    /// `debt_value / collateral_value`, `None` if the position has no collateral value.
+    pub ltv: Option<Decimal>,
+    /// Amount of loan that can still be borrowed.
+    pub max_borrowable: Uint128,
 }
 
 #[cw_serde]
@@ -77,12 +262,43 @@ pub enum GateCollectMsgsAllowed {
     BridgePosition {
         to_remote_addr: String,
         chain: String,
+        /// Remote market that receive the position. Required only if more than one remote contract is registered for `chain`.
+        remote_contract: Option<String>,
         native_info: Option<NativeInfo>,
+        /// Override the default timeout registered for `chain`.
+        timeout: Option<u64>,
+        /// Bridge the position of `on_behalf_of`, the sender has to be an approved operator.
+        /// `to_remote_addr` has to be the address registered by the owner with `SetRemoteAddr`.
+        on_behalf_of: Option<String>,
     },
 }
 
+#[cw_serde]
+pub struct BridgeFeeResponse {
+    /// Number of `gate` requests sent: one for every cw20 collateral plus the one sent by the market (that carries the native collateral).
+    pub requests: u64,
+    /// Total fee to send with the bridge, `protocol_fee` included. `None` if no fee is registered for the chain.
+    pub fee: Option<Coin>,
+    /// Part of `fee` kept by the protocol.
+    pub protocol_fee: Option<Coin>,
+}
+
+/// Fee received with a `BridgePosition`, splitted between the `gate` requests.
+#[cw_serde]
+#[derive(Default)]
+pub struct BridgeFeeSplit {
+    /// Fee sent with every cw20 `GateBridge`.
+    pub per_cw20: Option<Coin>,
+    /// Fee sent with the market `SendRequests`.
+    pub market: Option<Coin>,
+}
+
 #[cw_serde]
 pub struct BridgeMsgInfo {
+    /// Incremental id assigned by the market that sent the position.
+    pub bridge_id: u64,
+    /// Chain where the position is bridged.
+    pub chain: String,
     pub sender: String,
     pub receiver: String,
     pub src_position: Position,
@@ -0,0 +1,184 @@
+//! Mock of the `cw20-icg` contract.
+//!
+//! Implement only the cw20 msgs used by the market (`Transfer`, `Send`) and the `gate` integration:
+//! `GateBridge` burn the tokens and send a `Cw20GateMsgType::Bridge` to the remote `cw20-icg`,
+//! that mint them to the `remote_receiver`. If the request fails the tokens are minted back to the sender.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Storage, Uint128, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ReceiveMsg};
+use cw20_icg_pkg::{Cw20GateMsgType, ExecuteMsg, QueryMsg};
+use cw_multi_test::{Contract, ContractWrapper};
+use cw_storage_plus::{Item, Map};
+use gate_pkg::{ExecuteMsg as GateExecuteMsg, GateMsg, GateRequest, Permission};
+
+// --- CONSTANTS ---
+
+const BALANCES: Map<Addr, Uint128> = Map::new("balances");
+const GATE: Item<Addr> = Item::new("gate");
+const REMOTE_CONTRACTS: Map<String, String> = Map::new("remote_contracts");
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub initial_balances: Vec<Cw20Coin>,
+}
+
+pub fn contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+// --- ENTRY POINTS ---
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    for coin in msg.initial_balances {
+        mint(
+            deps.storage,
+            &deps.api.addr_validate(&coin.address)?,
+            coin.amount,
+        )?;
+    }
+
+    Ok(Response::new())
+}
+
+fn execute(deps: DepsMut, _env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::Transfer { recipient, amount } => {
+            burn(deps.storage, &info.sender, amount)?;
+            mint(deps.storage, &deps.api.addr_validate(&recipient)?, amount)?;
+
+            Ok(Response::new())
+        }
+        ExecuteMsg::Send {
+            contract,
+            amount,
+            msg,
+        } => {
+            burn(deps.storage, &info.sender, amount)?;
+            mint(deps.storage, &deps.api.addr_validate(&contract)?, amount)?;
+
+            Ok(Response::new().add_message(WasmMsg::Execute {
+                contract_addr: contract,
+                msg: Cw20ReceiveMsg {
+                    sender: info.sender.to_string(),
+                    amount,
+                    msg,
+                }
+                .into_binary()?,
+                funds: vec![],
+            }))
+        }
+        ExecuteMsg::RegisterGate { contract } => {
+            GATE.save(deps.storage, &contract)?;
+
+            Ok(Response::new())
+        }
+        ExecuteMsg::GateSetPermission { contract, chain } => {
+            REMOTE_CONTRACTS.save(deps.storage, chain.clone(), &contract)?;
+
+            Ok(Response::new().add_message(WasmMsg::Execute {
+                contract_addr: GATE.load(deps.storage)?.to_string(),
+                msg: to_binary(&GateExecuteMsg::SetPermission {
+                    permission: Permission::Permissioned {
+                        addresses: vec![contract],
+                    },
+                    chain,
+                })?,
+                funds: vec![],
+            }))
+        }
+        ExecuteMsg::GateBridge {
+            chain,
+            remote_receiver,
+            amount,
+        } => {
+            burn(deps.storage, &info.sender, amount)?;
+
+            Ok(Response::new().add_message(WasmMsg::Execute {
+                contract_addr: GATE.load(deps.storage)?.to_string(),
+                msg: to_binary(&GateExecuteMsg::SendRequests {
+                    requests: vec![GateRequest::SendMsg {
+                        msg: to_binary(&Cw20GateMsgType::Bridge {
+                            sender: info.sender.to_string(),
+                            receiver: remote_receiver,
+                            amount,
+                        })?,
+                        to_contract: REMOTE_CONTRACTS.load(deps.storage, chain.clone())?,
+                        send_native: None,
+                    }],
+                    chain,
+                    timeout: None,
+                })?,
+                funds: info.funds,
+            }))
+        }
+        ExecuteMsg::ReceiveGateMsg(msg) => {
+            if GATE.load(deps.storage)? != info.sender {
+                return Err(StdError::generic_err("Unauthorized"));
+            }
+
+            match msg {
+                GateMsg::ReceivedMsg { msg, .. } => {
+                    if let Cw20GateMsgType::Bridge {
+                        receiver, amount, ..
+                    } = from_binary(&msg)?
+                    {
+                        mint(deps.storage, &deps.api.addr_validate(&receiver)?, amount)?;
+                    }
+                }
+                GateMsg::RequestFailed {
+                    request: GateRequest::SendMsg { msg, .. },
+                } => {
+                    if let Cw20GateMsgType::Bridge { sender, amount, .. } = from_binary(&msg)? {
+                        mint(deps.storage, &deps.api.addr_validate(&sender)?, amount)?;
+                    }
+                }
+                _ => return Err(StdError::generic_err("Not implemented on mock_cw20_icg")),
+            }
+
+            Ok(Response::new())
+        }
+        _ => Err(StdError::generic_err("Not implemented on mock_cw20_icg")),
+    }
+}
+
+fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Balance { address } => to_binary(&BalanceResponse {
+            balance: BALANCES
+                .may_load(deps.storage, deps.api.addr_validate(&address)?)?
+                .unwrap_or_default(),
+        }),
+        QueryMsg::RemoteContract { chain } => {
+            to_binary(&REMOTE_CONTRACTS.load(deps.storage, chain)?)
+        }
+        QueryMsg::Gate {} => to_binary(&GATE.load(deps.storage)?),
+        _ => Err(StdError::generic_err("Not implemented on mock_cw20_icg")),
+    }
+}
+
+// --- FUNCTIONS ---
+
+fn mint(storage: &mut dyn Storage, address: &Addr, amount: Uint128) -> StdResult<()> {
+    BALANCES.update(storage, address.to_owned(), |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default().checked_add(amount)?)
+    })?;
+
+    Ok(())
+}
+
+fn burn(storage: &mut dyn Storage, address: &Addr, amount: Uint128) -> StdResult<()> {
+    BALANCES.update(storage, address.to_owned(), |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default().checked_sub(amount)?)
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,340 @@
+//! Mock of the `gate` contract.
+//!
+//! Every chain is simulated by a different instance of this contract inside the same `App`.
+//! As the real `gate`, all the `Requests` sent during a `CollectRequests` are packed in one packet.
+//! Packets are sent to the remote instance only when `Relay` is executed.
+//! If the execution on the remote instance fails, every sender receive a `RequestFailed`.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_binary, Addr, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, SubMsg, SubMsgResult, WasmMsg,
+};
+use cw_multi_test::{Contract, ContractWrapper};
+use cw_storage_plus::{Item, Map};
+use gate_pkg::{GateMsg, GateRequest, Permission, ReceiverExecuteMsg};
+
+// --- CONSTANTS ---
+
+const CHAIN: Item<String> = Item::new("chain");
+const REMOTE_GATES: Map<String, Addr> = Map::new("remote_gates");
+const PERMISSIONS: Map<(Addr, String), Permission> = Map::new("permissions");
+const COLLECTING: Item<Option<Packet>> = Item::new("collecting");
+const PACKET_ID: Item<u64> = Item::new("packet_id");
+const PENDING_PACKETS: Map<u64, Packet> = Map::new("pending_packets");
+const IN_FLIGHT_PACKETS: Map<u64, Packet> = Map::new("in_flight_packets");
+
+// --- MSGS ---
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub chain: String,
+}
+
+/// Superset of `gate_pkg::ExecuteMsg`, with the variants used only by the mock.
+#[cw_serde]
+pub enum ExecuteMsg {
+    CollectRequests {
+        to_contract: Addr,
+        msg: Binary,
+    },
+    SendRequests {
+        requests: Vec<GateRequest>,
+        chain: String,
+        timeout: Option<u64>,
+    },
+    SetPermission {
+        permission: Permission,
+        chain: String,
+    },
+    /// Register the mock `gate` that simulate `chain`.
+    RegisterChain {
+        chain: String,
+        gate: Addr,
+    },
+    /// Send all the pending packets to the remote `gate`.
+    Relay {},
+    PrivateSendCollectedMsgs {},
+    PrivateDeliver {
+        packet: Packet,
+    },
+}
+
+#[cw_serde]
+pub enum QueryMsg {
+    PendingPackets {},
+}
+
+#[cw_serde]
+pub struct Packet {
+    pub from_chain: String,
+    pub to_chain: String,
+    /// List of `(sender, request)`.
+    pub requests: Vec<(String, GateRequest)>,
+}
+
+pub fn contract() -> Box<dyn Contract<cosmwasm_std::Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply))
+}
+
+// --- ENTRY POINTS ---
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    CHAIN.save(deps.storage, &msg.chain)?;
+    COLLECTING.save(deps.storage, &None)?;
+    PACKET_ID.save(deps.storage, &0)?;
+
+    Ok(Response::new())
+}
+
+fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    match msg {
+        ExecuteMsg::CollectRequests { to_contract, msg } => {
+            run_collect_requests(deps, env, info, to_contract, msg)
+        }
+        ExecuteMsg::SendRequests {
+            requests, chain, ..
+        } => run_send_requests(deps, info.sender, requests, chain),
+        ExecuteMsg::SetPermission { permission, chain } => {
+            PERMISSIONS.save(deps.storage, (info.sender, chain), &permission)?;
+            Ok(Response::new())
+        }
+        ExecuteMsg::RegisterChain { chain, gate } => {
+            REMOTE_GATES.save(deps.storage, chain, &gate)?;
+            Ok(Response::new())
+        }
+        ExecuteMsg::Relay {} => run_relay(deps),
+        ExecuteMsg::PrivateSendCollectedMsgs {} => {
+            if info.sender != env.contract.address {
+                return Err(StdError::generic_err("Unauthorized"));
+            }
+
+            let packet = COLLECTING.load(deps.storage)?;
+
+            COLLECTING.save(deps.storage, &None)?;
+
+            if let Some(packet) = packet.filter(|packet| !packet.requests.is_empty()) {
+                save_pending_packet(deps, packet)?;
+            }
+
+            Ok(Response::new())
+        }
+        ExecuteMsg::PrivateDeliver { packet } => run_deliver(deps, info.sender, packet),
+    }
+}
+
+fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::PendingPackets {} => to_binary(
+            &PENDING_PACKETS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| item.map(|(_, packet)| packet))
+                .collect::<StdResult<Vec<Packet>>>()?,
+        ),
+    }
+}
+
+fn reply(deps: DepsMut, _env: Env, reply: Reply) -> StdResult<Response> {
+    let packet = IN_FLIGHT_PACKETS.load(deps.storage, reply.id)?;
+
+    IN_FLIGHT_PACKETS.remove(deps.storage, reply.id);
+
+    match reply.result {
+        SubMsgResult::Ok(_) => Ok(Response::new()),
+        SubMsgResult::Err(err) => {
+            let mut msgs: Vec<CosmosMsg> = vec![];
+
+            for (sender, request) in packet.requests {
+                let funds = request
+                    .send_native()
+                    .map(|native| vec![native.coin])
+                    .unwrap_or_default();
+
+                msgs.push(
+                    WasmMsg::Execute {
+                        contract_addr: sender,
+                        msg: to_binary(&ReceiverExecuteMsg::ReceiveGateMsg(
+                            GateMsg::RequestFailed { request },
+                        ))?,
+                        funds,
+                    }
+                    .into(),
+                );
+            }
+
+            Ok(Response::new()
+                .add_messages(msgs)
+                .add_attribute("packet_failed", err))
+        }
+    }
+}
+
+// --- RUN ---
+
+fn run_collect_requests(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to_contract: Addr,
+    msg: Binary,
+) -> StdResult<Response> {
+    let from_chain = CHAIN.load(deps.storage)?;
+
+    COLLECTING.save(
+        deps.storage,
+        &Some(Packet {
+            from_chain,
+            to_chain: String::new(),
+            requests: vec![],
+        }),
+    )?;
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: to_contract.to_string(),
+            msg: to_binary(&ReceiverExecuteMsg::ReceiveGateMsg(
+                GateMsg::CollectRequests {
+                    sender: info.sender,
+                    msg,
+                },
+            ))?,
+            funds: info.funds,
+        })
+        .add_message(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&ExecuteMsg::PrivateSendCollectedMsgs {})?,
+            funds: vec![],
+        }))
+}
+
+fn run_send_requests(
+    deps: DepsMut,
+    sender: Addr,
+    requests: Vec<GateRequest>,
+    chain: String,
+) -> StdResult<Response> {
+    let requests: Vec<(String, GateRequest)> = requests
+        .into_iter()
+        .map(|request| (sender.to_string(), request))
+        .collect();
+
+    match COLLECTING.load(deps.storage)? {
+        Some(mut packet) => {
+            if !packet.to_chain.is_empty() && packet.to_chain != chain {
+                return Err(StdError::generic_err("Multiple chains in the same collect"));
+            }
+
+            packet.to_chain = chain;
+            packet.requests.extend(requests);
+
+            COLLECTING.save(deps.storage, &Some(packet))?;
+        }
+        None => {
+            let packet = Packet {
+                from_chain: CHAIN.load(deps.storage)?,
+                to_chain: chain,
+                requests,
+            };
+
+            save_pending_packet(deps, packet)?;
+        }
+    }
+
+    Ok(Response::new())
+}
+
+fn run_relay(deps: DepsMut) -> StdResult<Response> {
+    let packets = PENDING_PACKETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(u64, Packet)>>>()?;
+
+    let mut msgs: Vec<SubMsg> = vec![];
+
+    for (id, packet) in packets {
+        PENDING_PACKETS.remove(deps.storage, id);
+        IN_FLIGHT_PACKETS.save(deps.storage, id, &packet)?;
+
+        msgs.push(SubMsg::reply_always(
+            WasmMsg::Execute {
+                contract_addr: REMOTE_GATES
+                    .load(deps.storage, packet.to_chain.clone())?
+                    .to_string(),
+                msg: to_binary(&ExecuteMsg::PrivateDeliver { packet })?,
+                funds: vec![],
+            },
+            id,
+        ));
+    }
+
+    Ok(Response::new().add_submessages(msgs))
+}
+
+fn run_deliver(deps: DepsMut, sender: Addr, packet: Packet) -> StdResult<Response> {
+    if REMOTE_GATES.load(deps.storage, packet.from_chain.clone())? != sender {
+        return Err(StdError::generic_err("Unauthorized"));
+    }
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+
+    for (sender, request) in packet.requests {
+        match request {
+            GateRequest::SendMsg {
+                msg,
+                to_contract,
+                send_native,
+            } => {
+                let permission = PERMISSIONS.may_load(
+                    deps.storage,
+                    (Addr::unchecked(&to_contract), packet.from_chain.clone()),
+                )?;
+
+                match permission {
+                    Some(Permission::Permissionless {}) => {}
+                    Some(Permission::Permissioned { addresses }) if addresses.contains(&sender) => {
+                    }
+                    _ => return Err(StdError::generic_err("Permission denied")),
+                }
+
+                // The remote gate hold the vouchers of the native coins
+                let funds: Vec<Coin> = send_native
+                    .map(|native| {
+                        vec![Coin {
+                            denom: native.dest_denom,
+                            amount: native.coin.amount,
+                        }]
+                    })
+                    .unwrap_or_default();
+
+                msgs.push(
+                    WasmMsg::Execute {
+                        contract_addr: to_contract,
+                        msg: to_binary(&ReceiverExecuteMsg::ReceiveGateMsg(
+                            GateMsg::ReceivedMsg { sender, msg },
+                        ))?,
+                        funds,
+                    }
+                    .into(),
+                );
+            }
+            GateRequest::Query { .. } => {
+                return Err(StdError::generic_err("Query not supported by mock gate"))
+            }
+        }
+    }
+
+    Ok(Response::new().add_messages(msgs))
+}
+
+// --- FUNCTIONS ---
+
+fn save_pending_packet(deps: DepsMut, packet: Packet) -> StdResult<()> {
+    let id = PACKET_ID.load(deps.storage)? + 1;
+
+    PACKET_ID.save(deps.storage, &id)?;
+    PENDING_PACKETS.save(deps.storage, id, &packet)
+}
@@ -0,0 +1,405 @@
+//! Multi-contract test suite.
+//!
+//! Two chains (`CHAIN_A` and `CHAIN_B`) are simulated in the same `App`.
+//! Every chain has its own mock `gate`, market and mock `cw20-icg`, registered with the counterpart on the other chain,
+//! and its own oracle that prices every asset 1.
+
+mod mock_cw20_icg;
+mod mock_gate;
+mod tests;
+
+use cosmwasm_std::{coin, to_binary, Addr, Coin, Decimal, Empty, Uint128};
+use cw20::{BalanceResponse, Cw20Coin};
+use cw_multi_test::{
+    error::AnyResult, App, AppBuilder, AppResponse, Contract, ContractWrapper, Executor,
+};
+
+use crate::{
+    contract::{execute, instantiate, query, reply},
+    msgs::{
+        Cw20MsgType, ExecuteMsg, GateCollectMsgsAllowed, InstantiateMsg, NativeInfo, Position,
+        QueryMsg,
+    },
+};
+
+use self::mock_gate::Packet;
+
+pub const CHAIN_A: &str = "chain-a";
+pub const CHAIN_B: &str = "chain-b";
+
+/// Native coin deposited on `CHAIN_A`.
+pub const NATIVE_DENOM: &str = "uAtom";
+/// Denom of `NATIVE_DENOM` on `CHAIN_B`. The mock `gate` of `CHAIN_B` hold a balance of it.
+pub const VOUCHER_DENOM: &str = "ibc/UATOM";
+pub const FEE_DENOM: &str = "uluna";
+
+const INITIAL_BALANCE: u128 = 1_000_000;
+
+/// Oracle assets of the loan and of the collaterals.
+const LOAN_ASSET: &str = "usd";
+const CW20_ASSET: &str = "cw20";
+const NATIVE_ASSET: &str = "atom";
+const COLLATERAL_LTV: Decimal = Decimal::percent(50);
+
+fn market_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply))
+}
+
+fn oracle_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        oracle::contract::execute,
+        oracle::contract::instantiate,
+        oracle::contract::query,
+    ))
+}
+
+pub struct ChainContracts {
+    pub name: String,
+    pub gate: Addr,
+    pub market: Addr,
+    pub cw20: Addr,
+    pub oracle: Addr,
+}
+
+pub struct Suite {
+    pub app: App,
+    pub owner: Addr,
+    /// User on `CHAIN_A`.
+    pub user: Addr,
+    /// User on `CHAIN_B`.
+    pub remote_user: Addr,
+    pub chain_a: ChainContracts,
+    pub chain_b: ChainContracts,
+}
+
+impl Default for Suite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Suite {
+    pub fn new() -> Suite {
+        let owner = Addr::unchecked("owner");
+        let user = Addr::unchecked("user000");
+        let remote_user = Addr::unchecked("user001");
+
+        let mut app = AppBuilder::new().build(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &user,
+                    vec![
+                        coin(INITIAL_BALANCE, NATIVE_DENOM),
+                        coin(INITIAL_BALANCE, FEE_DENOM),
+                    ],
+                )
+                .unwrap();
+        });
+
+        let gate_code_id = app.store_code(mock_gate::contract());
+        let market_code_id = app.store_code(market_contract());
+        let cw20_code_id = app.store_code(mock_cw20_icg::contract());
+        let oracle_code_id = app.store_code(oracle_contract());
+
+        let setup_chain = |app: &mut App, name: &str, native_denom: &str| -> ChainContracts {
+            let gate = app
+                .instantiate_contract(
+                    gate_code_id,
+                    owner.clone(),
+                    &mock_gate::InstantiateMsg {
+                        chain: name.to_string(),
+                    },
+                    &[],
+                    "gate",
+                    None,
+                )
+                .unwrap();
+
+            let market = app
+                .instantiate_contract(
+                    market_code_id,
+                    owner.clone(),
+                    &InstantiateMsg {},
+                    &[],
+                    "market",
+                    None,
+                )
+                .unwrap();
+
+            let cw20 = app
+                .instantiate_contract(
+                    cw20_code_id,
+                    owner.clone(),
+                    &mock_cw20_icg::InstantiateMsg {
+                        initial_balances: vec![Cw20Coin {
+                            address: user.to_string(),
+                            amount: Uint128::from(INITIAL_BALANCE),
+                        }],
+                    },
+                    &[],
+                    "cw20-icg",
+                    None,
+                )
+                .unwrap();
+
+            app.execute_contract(
+                owner.clone(),
+                market.clone(),
+                &ExecuteMsg::RegisterGate {
+                    contract: gate.clone(),
+                },
+                &[],
+            )
+            .unwrap();
+
+            app.execute_contract(
+                owner.clone(),
+                cw20.clone(),
+                &cw20_icg_pkg::ExecuteMsg::RegisterGate {
+                    contract: gate.clone(),
+                },
+                &[],
+            )
+            .unwrap();
+
+            // Every asset is priced 1 by the oracle, the owner is the feeder
+
+            let oracle = app
+                .instantiate_contract(
+                    oracle_code_id,
+                    owner.clone(),
+                    &oracle::msgs::InstantiateMsg {},
+                    &[],
+                    "oracle",
+                    None,
+                )
+                .unwrap();
+
+            for asset in [LOAN_ASSET, CW20_ASSET, NATIVE_ASSET] {
+                for msg in [
+                    oracle::msgs::ExecuteMsg::RegisterAsset {
+                        asset: asset.to_string(),
+                        feeders: vec![owner.clone()],
+                        feed_config: None,
+                        metadata: None,
+                    },
+                    oracle::msgs::ExecuteMsg::FeedPrice {
+                        asset: asset.to_string(),
+                        price: Decimal::one(),
+                    },
+                ] {
+                    app.execute_contract(owner.clone(), oracle.clone(), &msg, &[])
+                        .unwrap();
+                }
+            }
+
+            for msg in [
+                ExecuteMsg::RegisterOracle {
+                    contract: oracle.clone(),
+                    loan_asset: LOAN_ASSET.to_string(),
+                },
+                ExecuteMsg::RegisterCollateral {
+                    denom: cw20.to_string(),
+                    oracle_asset: CW20_ASSET.to_string(),
+                    ltv: COLLATERAL_LTV,
+                },
+                ExecuteMsg::RegisterCollateral {
+                    denom: native_denom.to_string(),
+                    oracle_asset: NATIVE_ASSET.to_string(),
+                    ltv: COLLATERAL_LTV,
+                },
+            ] {
+                app.execute_contract(owner.clone(), market.clone(), &msg, &[])
+                    .unwrap();
+            }
+
+            ChainContracts {
+                name: name.to_string(),
+                gate,
+                market,
+                cw20,
+                oracle,
+            }
+        };
+
+        let chain_a = setup_chain(&mut app, CHAIN_A, NATIVE_DENOM);
+        let chain_b = setup_chain(&mut app, CHAIN_B, VOUCHER_DENOM);
+
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(
+                    storage,
+                    &chain_b.gate,
+                    vec![coin(INITIAL_BALANCE, VOUCHER_DENOM)],
+                )
+                .unwrap();
+        });
+
+        for (local, remote) in [(&chain_a, &chain_b), (&chain_b, &chain_a)] {
+            app.execute_contract(
+                owner.clone(),
+                local.gate.clone(),
+                &mock_gate::ExecuteMsg::RegisterChain {
+                    chain: remote.name.clone(),
+                    gate: remote.gate.clone(),
+                },
+                &[],
+            )
+            .unwrap();
+
+            app.execute_contract(
+                owner.clone(),
+                local.market.clone(),
+                &ExecuteMsg::GateSetPermission {
+                    contract: remote.market.to_string(),
+                    chain: remote.name.clone(),
+                },
+                &[],
+            )
+            .unwrap();
+
+            app.execute_contract(
+                owner.clone(),
+                local.cw20.clone(),
+                &cw20_icg_pkg::ExecuteMsg::GateSetPermission {
+                    contract: remote.cw20.to_string(),
+                    chain: remote.name.clone(),
+                },
+                &[],
+            )
+            .unwrap();
+        }
+
+        Suite {
+            app,
+            owner,
+            user,
+            remote_user,
+            chain_a,
+            chain_b,
+        }
+    }
+
+    /// Deposit `amount` of the `CHAIN_A` cw20 in the `CHAIN_A` market.
+    pub fn deposit_cw20(&mut self, amount: u128) {
+        self.app
+            .execute_contract(
+                self.user.clone(),
+                self.chain_a.cw20.clone(),
+                &cw20_icg_pkg::ExecuteMsg::Send {
+                    contract: self.chain_a.market.to_string(),
+                    amount: Uint128::from(amount),
+                    msg: to_binary(&Cw20MsgType::Deposit {}).unwrap(),
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    /// Deposit `amount` of `NATIVE_DENOM` in the `CHAIN_A` market.
+    pub fn deposit_native(&mut self, amount: u128) {
+        self.app
+            .execute_contract(
+                self.user.clone(),
+                self.chain_a.market.clone(),
+                &ExecuteMsg::Deposit {},
+                &[coin(amount, NATIVE_DENOM)],
+            )
+            .unwrap();
+    }
+
+    pub fn borrow(&mut self, amount: u128) {
+        self.app
+            .execute_contract(
+                self.user.clone(),
+                self.chain_a.market.clone(),
+                &ExecuteMsg::IncreaseLoan {
+                    amount: Uint128::from(amount),
+                    on_behalf_of: None,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+
+    /// Bridge the position of `user` from `CHAIN_A` to `to_remote_addr` on `CHAIN_B`, passing from the `CHAIN_A` `gate`.
+    pub fn bridge_position(
+        &mut self,
+        to_remote_addr: impl Into<String>,
+        fee: u128,
+    ) -> AnyResult<AppResponse> {
+        let with_native = self
+            .position(&self.chain_a.market, &self.user)
+            .map(|position| position.collaterals.contains_key(NATIVE_DENOM))
+            .unwrap_or_default();
+
+        self.app.execute_contract(
+            self.user.clone(),
+            self.chain_a.gate.clone(),
+            &mock_gate::ExecuteMsg::CollectRequests {
+                to_contract: self.chain_a.market.clone(),
+                msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+                    to_remote_addr: to_remote_addr.into(),
+                    chain: CHAIN_B.to_string(),
+                    remote_contract: None,
+                    native_info: with_native.then(|| NativeInfo {
+                        path_middle_forward: vec![],
+                        dest_denom: VOUCHER_DENOM.to_string(),
+                        channel_id: "channel-0".to_string(),
+                        timeout: None,
+                    }),
+                    timeout: None,
+                    on_behalf_of: None,
+                })
+                .unwrap(),
+            },
+            &[coin(fee, FEE_DENOM)],
+        )
+    }
+
+    /// Send the pending packets of `gate` to the remote chain.
+    pub fn relay(&mut self, gate: &Addr) -> AppResponse {
+        self.app
+            .execute_contract(
+                self.owner.clone(),
+                gate.clone(),
+                &mock_gate::ExecuteMsg::Relay {},
+                &[],
+            )
+            .unwrap()
+    }
+
+    pub fn pending_packets(&self, gate: &Addr) -> Vec<Packet> {
+        self.app
+            .wrap()
+            .query_wasm_smart(gate, &mock_gate::QueryMsg::PendingPackets {})
+            .unwrap()
+    }
+
+    pub fn position(&self, market: &Addr, user: &Addr) -> Option<Position> {
+        self.app
+            .wrap()
+            .query_wasm_smart(market, &QueryMsg::Position { user: user.clone() })
+            .ok()
+    }
+
+    pub fn cw20_balance(&self, cw20: &Addr, address: &Addr) -> Uint128 {
+        self.app
+            .wrap()
+            .query_wasm_smart::<BalanceResponse>(
+                cw20,
+                &cw20_icg_pkg::QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap()
+            .balance
+    }
+
+    pub fn balance(&self, address: &Addr, denom: &str) -> Coin {
+        self.app.wrap().query_balance(address, denom).unwrap()
+    }
+}
@@ -0,0 +1,182 @@
+use cosmwasm_std::{Addr, Uint128};
+use cw_multi_test::Executor;
+
+use crate::msgs::ExecuteMsg;
+
+use super::{Suite, CHAIN_A, NATIVE_DENOM, VOUCHER_DENOM};
+
+#[test]
+fn bridge_position() {
+    let mut suite = Suite::new();
+
+    suite.deposit_cw20(100);
+    suite.deposit_native(50);
+    suite.borrow(10);
+
+    suite
+        .bridge_position(suite.remote_user.to_string(), 20)
+        .unwrap();
+
+    // The position is removed on CHAIN_A and the cw20 are burned
+
+    assert!(suite.position(&suite.chain_a.market, &suite.user).is_none());
+    assert_eq!(
+        suite.cw20_balance(&suite.chain_a.cw20, &suite.chain_a.market),
+        Uint128::zero()
+    );
+
+    // All the requests are collected in one packet
+
+    let packets = suite.pending_packets(&suite.chain_a.gate);
+
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].requests.len(), 2);
+
+    // RELAY
+
+    let gate = suite.chain_a.gate.clone();
+    suite.relay(&gate);
+
+    assert!(suite.pending_packets(&suite.chain_a.gate).is_empty());
+
+    let position = suite
+        .position(&suite.chain_b.market, &suite.remote_user)
+        .unwrap();
+
+    assert_eq!(position.loan, Uint128::from(10_u128));
+    assert_eq!(
+        position.collaterals[suite.chain_b.cw20.as_str()],
+        Uint128::from(100_u128)
+    );
+    assert_eq!(position.collaterals[VOUCHER_DENOM], Uint128::from(50_u128));
+
+    assert_eq!(
+        suite.cw20_balance(&suite.chain_b.cw20, &suite.chain_b.market),
+        Uint128::from(100_u128)
+    );
+    assert_eq!(
+        suite.balance(&suite.chain_b.market, VOUCHER_DENOM).amount,
+        Uint128::from(50_u128)
+    );
+
+    // WITHDRAW ON CHAIN_B
+
+    suite
+        .app
+        .execute_contract(
+            suite.remote_user.clone(),
+            suite.chain_b.market.clone(),
+            &ExecuteMsg::Withdraw {
+                denom: suite.chain_b.cw20.to_string(),
+                amount: Some(Uint128::from(40_u128)),
+                on_behalf_of: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    assert_eq!(
+        suite.cw20_balance(&suite.chain_b.cw20, &suite.remote_user),
+        Uint128::from(40_u128)
+    );
+    assert_eq!(
+        suite
+            .position(&suite.chain_b.market, &suite.remote_user)
+            .unwrap()
+            .collaterals[suite.chain_b.cw20.as_str()],
+        Uint128::from(60_u128)
+    );
+}
+
+fn assert_position_reverted(suite: &Suite) {
+    let position = suite.position(&suite.chain_a.market, &suite.user).unwrap();
+
+    assert_eq!(position.loan, Uint128::from(10_u128));
+    assert_eq!(
+        position.collaterals[suite.chain_a.cw20.as_str()],
+        Uint128::from(100_u128)
+    );
+    assert_eq!(position.collaterals[NATIVE_DENOM], Uint128::from(50_u128));
+
+    assert_eq!(
+        suite.cw20_balance(&suite.chain_a.cw20, &suite.chain_a.market),
+        Uint128::from(100_u128)
+    );
+    assert_eq!(
+        suite.balance(&suite.chain_a.market, NATIVE_DENOM).amount,
+        Uint128::from(50_u128)
+    );
+
+    assert!(suite
+        .position(&suite.chain_b.market, &suite.remote_user)
+        .is_none());
+    assert_eq!(
+        suite.cw20_balance(&suite.chain_b.cw20, &suite.chain_b.market),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn bridge_position_revert() {
+    let mut suite = Suite::new();
+
+    suite.deposit_cw20(100);
+    suite.deposit_native(50);
+    suite.borrow(10);
+
+    // The remote market fails to validate the receiver
+
+    suite.bridge_position("INVALID", 20).unwrap();
+
+    let gate = suite.chain_a.gate.clone();
+    suite.relay(&gate);
+
+    assert_position_reverted(&suite);
+}
+
+#[test]
+fn bridge_position_permission_revoked() {
+    let mut suite = Suite::new();
+
+    suite.deposit_cw20(100);
+    suite.deposit_native(50);
+    suite.borrow(10);
+
+    suite
+        .bridge_position(suite.remote_user.to_string(), 20)
+        .unwrap();
+
+    // The remote market remove CHAIN_A before the packet is relayed
+
+    suite
+        .app
+        .execute_contract(
+            suite.owner.clone(),
+            suite.chain_b.market.clone(),
+            &ExecuteMsg::RemoveChain {
+                chain: CHAIN_A.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let gate = suite.chain_a.gate.clone();
+    suite.relay(&gate);
+
+    assert_position_reverted(&suite);
+}
+
+#[test]
+fn bridge_position_without_position() {
+    let mut suite = Suite::new();
+
+    let err = suite
+        .bridge_position(suite.remote_user.to_string(), 20)
+        .unwrap_err();
+
+    assert!(err.root_cause().to_string().contains("not found"));
+    assert!(suite.pending_packets(&suite.chain_a.gate).is_empty());
+    assert!(suite
+        .position(&suite.chain_a.market, &Addr::unchecked("user000"))
+        .is_none());
+}
@@ -1,11 +1,111 @@
 use crate::msgs::Position;
-use cosmwasm_std::Addr;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+use cw20::Expiration;
 use cw_storage_plus::{Item, Map};
 
 // --- CONSTANTS ---
 
+/// Denom used in the `wasm-market` events to report the changes of the loan.
+pub const LOAN_DENOM: &str = "loan";
+
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+
 pub const OWNER: Item<Addr> = Item::new("addr");
 
 pub const GATE: Item<Addr> = Item::new("gate");
 pub const POSITIONS: Map<Addr, Position> = Map::new("position");
-pub const CHAINS_CONTRACT: Map<String, String> = Map::new("chains_contracts");
+/// Id of the last bridged position.
+pub const BRIDGE_ID: Item<u64> = Item::new("bridge_id");
+pub const CHAINS_CONTRACT: Map<String, Vec<String>> = Map::new("chains_contracts");
+pub const ORACLE: Item<Addr> = Item::new("oracle");
+/// Oracle asset used to price the loan.
+pub const LOAN_ASSET: Item<String> = Item::new("loan_asset");
+pub const COLLATERALS_CONFIG: Map<String, CollateralConfig> = Map::new("collaterals_config");
+/// Collateral categories (e-mode) a position can opt into.
+pub const CATEGORIES: Map<String, CollateralCategory> = Map::new("categories");
+
+pub const CHAINS_CONFIG: Map<String, ChainConfig> = Map::new("chains_config");
+
+/// Operators approved by a user, keyed by `(user, operator)`.
+pub const APPROVALS: Map<(Addr, Addr), Approval> = Map::new("approvals");
+/// Address of a user on a remote chain, the only one the operators can bridge the position to. Keyed by `(user, chain)`.
+pub const REMOTE_ADDRS: Map<(Addr, String), String> = Map::new("remote_addrs");
+
+/// Fee paid on the amount borrowed with a `FlashLoan`.
+pub const FLASH_LOAN_FEE: Item<Decimal> = Item::new("flash_loan_fee");
+/// Flash loan in progress, removed on the reply of the callback.
+pub const FLASH_LOAN: Item<FlashLoanState> = Item::new("flash_loan");
+/// Amount of every denom owned by the protocol.
+pub const RESERVES: Map<String, Uint128> = Map::new("reserves");
+/// Amount of every denom deposited as collateral by the positions, it can't be flash loaned.
+pub const TOTAL_DEPOSITS: Map<String, Uint128> = Map::new("total_deposits");
+
+/// Interest rate of the loans and share of the interest kept by the protocol.
+pub const INTEREST_CONFIG: Item<InterestConfig> = Item::new("interest_config");
+/// Interest cumulated by a loan since the first accrual.
+pub const BORROW_INDEX: Item<BorrowIndex> = Item::new("borrow_index");
+/// `BORROW_INDEX` at the last interest accrual of every position.
+pub const POSITION_BORROW_INDEX: Map<Addr, Decimal> = Map::new("position_borrow_index");
+
+#[cw_serde]
+#[derive(Default)]
+pub struct ChainConfig {
+    /// Default timeout used for the `gate` requests sent to the chain.
+    pub timeout: Option<u64>,
+    /// Fee expected for every `gate` request sent to the chain.
+    pub fee_per_request: Option<Coin>,
+    /// Fee kept by the protocol for every position bridged to the chain, on top of the `gate` fees.
+    pub bridge_fee: Option<Coin>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct InterestConfig {
+    /// Yearly interest rate charged on the loans.
+    pub borrow_rate: Decimal,
+    /// Share of the interest kept by the protocol, added to the `LOAN_DENOM` reserves.
+    pub reserve_factor: Decimal,
+}
+
+#[cw_serde]
+pub struct BorrowIndex {
+    pub index: Decimal,
+    pub last_update: Timestamp,
+}
+
+#[cw_serde]
+pub struct CollateralConfig {
+    /// Oracle asset used to price the collateral.
+    pub oracle_asset: String,
+    /// Max loan to value allowed for the collateral.
+    pub ltv: Decimal,
+}
+
+#[cw_serde]
+pub struct CollateralCategory {
+    /// Collaterals allowed in a position of the category.
+    pub collaterals: Vec<String>,
+    /// Max loan to value allowed for the collaterals of the category, replace the ltv of the single collaterals.
+    pub ltv: Decimal,
+    /// Loan to value over which the position can be liquidated.
+    pub liquidation_threshold: Decimal,
+}
+
+#[cw_serde]
+pub struct FlashLoanState {
+    pub receiver: Addr,
+    pub denom: String,
+    pub amount: Uint128,
+    pub fee: Uint128,
+    /// Balance of `denom` the market must hold when the callback ends: the balance before the loan plus the fee.
+    pub expected_balance: Uint128,
+}
+
+#[cw_serde]
+pub struct Approval {
+    /// Max amount of every denom the operator can withdraw, `LOAN_DENOM` for the loan it can borrow and repay.
+    /// `None` if the operator has no limits.
+    pub allowances: Option<Vec<Coin>>,
+    pub expires: Expiration,
+}
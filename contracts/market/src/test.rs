@@ -2,16 +2,31 @@ use std::collections::HashMap;
 
 use cosmwasm_std::{
     from_binary,
-    testing::{mock_dependencies, mock_env, mock_info},
-    to_binary, Addr, Coin, Uint128,
+    testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info, MockApi,
+        MockQuerier, MockStorage,
+    },
+    to_binary, Addr, BankMsg, Coin, ContractResult, CosmosMsg, Decimal, Event, MessageInfo,
+    OwnedDeps, Reply, Response, SubMsg, SubMsgResponse, SubMsgResult, SystemResult, Uint128,
+    WasmMsg, WasmQuery,
 };
 
-use gate_pkg::GateMsg;
+use cw20::Expiration;
+use cw_storage_plus::Map;
+use gate_pkg::{ExecuteMsg as GateExecuteMsg, GateMsg, Permission};
+use proptest::prelude::*;
 
 use crate::{
-    contract::{execute, instantiate, query},
+    contract::{execute, instantiate, migrate, query, reply, split_bridge_fee},
+    errors::ContractError,
     msgs::{
-        self, BridgeMsgInfo, ExecuteMsg, GateCollectMsgsAllowed, InstantiateMsg, Position, QueryMsg,
+        self, BridgeFeeResponse, BridgeMsgInfo, ExecuteMsg, FlashLoanReceiverMsg,
+        GateCollectMsgsAllowed, HealthResponse, InstantiateMsg, MigrateMsg, MsgReplyID, Position,
+        QueryMsg,
+    },
+    state::{
+        Approval, ChainConfig, CollateralCategory, CHAINS_CONTRACT, FLASH_LOAN, LOAN_DENOM,
+        POSITIONS, RESERVES, SECONDS_PER_YEAR, TOTAL_DEPOSITS,
     },
 };
 
@@ -99,12 +114,15 @@ fn main() {
         msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
             to_remote_addr: "remote000".to_string(),
             chain: remote_chain,
+            remote_contract: None,
             native_info: Some(msgs::NativeInfo {
                 path_middle_forward: vec![],
                 dest_denom: "ibc/uatom".to_string(),
                 channel_id: "channel-1".to_string(),
                 timeout: None,
             }),
+            timeout: None,
+            on_behalf_of: None,
         })
         .unwrap(),
     });
@@ -134,15 +152,19 @@ fn main() {
     let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::ReceivedMsg {
         sender: remote_market_contract.to_string(),
         msg: to_binary(&BridgeMsgInfo {
+            bridge_id: 1,
+            chain: "local_chain".to_string(),
             sender: "remote_user".to_string(),
             receiver: "local_user".to_string(),
             dest_position: Position {
                 loan: Uint128::from(500_u128),
                 collaterals: collaterals.clone(),
+                category: None,
             },
             src_position: Position {
                 loan: Uint128::from(500_u128),
                 collaterals,
+                category: None,
             },
         })
         .unwrap(),
@@ -162,3 +184,2243 @@ fn main() {
 
     println!("{:?}", res)
 }
+
+fn assert_gate_permission(res: &Response, expected: Vec<&str>) {
+    match &res.messages.first().unwrap().msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_binary::<GateExecuteMsg>(msg).unwrap() {
+                GateExecuteMsg::SetPermission {
+                    permission: Permission::Permissioned { addresses },
+                    ..
+                } => assert_eq!(addresses, expected),
+                _ => panic!("Unexpected gate msg"),
+            }
+        }
+        _ => panic!("Unexpected msg"),
+    }
+}
+
+#[test]
+fn remote_contracts() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    let remote_chain = "injective".to_string();
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    // REGISTER TWO REMOTE MARKETS
+
+    for (contract, expected) in [
+        ("remote_market_v1", vec!["remote_market_v1"]),
+        (
+            "remote_market_v2",
+            vec!["remote_market_v1", "remote_market_v2"],
+        ),
+    ] {
+        let msg = ExecuteMsg::GateSetPermission {
+            contract: contract.to_string(),
+            chain: remote_chain.clone(),
+        };
+
+        let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+        assert_gate_permission(&res, expected);
+    }
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_market_v1".to_string(),
+        chain: remote_chain.clone(),
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::RemoteContractAlredyRegistered { .. }
+    ));
+
+    // BRIDGE WITHOUT SPECIFY THE REMOTE CONTRACT
+
+    let msg = ExecuteMsg::Deposit {};
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            user_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(100_u128),
+            }],
+        ),
+        msg,
+    )
+    .unwrap();
+
+    let bridge_msg = |remote_contract: Option<String>| {
+        ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+            sender: user_info.sender.clone(),
+            msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+                to_remote_addr: "remote000".to_string(),
+                chain: remote_chain.clone(),
+                remote_contract,
+                native_info: Some(msgs::NativeInfo {
+                    path_middle_forward: vec![],
+                    dest_denom: "ibc/uatom".to_string(),
+                    channel_id: "channel-1".to_string(),
+                    timeout: None,
+                }),
+                timeout: None,
+                on_behalf_of: None,
+            })
+            .unwrap(),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        bridge_msg(None),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::RemoteContractRequired { .. }));
+
+    // REMOVE ONE REMOTE MARKET
+
+    let msg = ExecuteMsg::GateRemovePermission {
+        contract: "remote_market_v1".to_string(),
+        chain: remote_chain.clone(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    assert_gate_permission(&res, vec!["remote_market_v2"]);
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        bridge_msg(Some("remote_market_v1".to_string())),
+    )
+    .unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::RemoteContractNotRegistered { .. }
+    ));
+
+    execute(deps.as_mut(), env.clone(), gate_info, bridge_msg(None)).unwrap();
+
+    // REMOVE CHAIN
+
+    let msg = ExecuteMsg::RemoveChain {
+        chain: remote_chain.clone(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    assert_gate_permission(&res, vec![]);
+
+    let msg = QueryMsg::RemoteContracts {
+        chain: remote_chain,
+    };
+
+    let res: Vec<String> = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+
+    assert!(res.is_empty());
+}
+
+#[test]
+fn chain_config() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    let remote_chain = "injective".to_string();
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    // SET CHAIN CONFIG ON A NOT REGISTERED CHAIN
+
+    let config_msg = ExecuteMsg::SetChainConfig {
+        chain: remote_chain.clone(),
+        timeout: Some(100),
+        fee_per_request: Some(Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::from(10_u128),
+        }),
+        bridge_fee: None,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        config_msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::ChainNotRegistered { .. }));
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_market".to_string(),
+        chain: remote_chain.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), config_msg).unwrap();
+
+    // QUERY BRIDGE FEE
+
+    let msg = QueryMsg::BridgeFee {
+        chain: remote_chain.clone(),
+        cw20_collaterals: 2,
+    };
+
+    let res: BridgeFeeResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+
+    assert_eq!(res.requests, 3);
+    assert_eq!(res.fee.unwrap().amount, Uint128::from(30_u128));
+
+    // BRIDGE A NATIVE POSITION
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            user_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(100_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap();
+
+    let bridge_msg = ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+        sender: user_info.sender,
+        msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+            to_remote_addr: "remote000".to_string(),
+            chain: remote_chain.clone(),
+            remote_contract: None,
+            native_info: Some(msgs::NativeInfo {
+                path_middle_forward: vec![],
+                dest_denom: "ibc/uatom".to_string(),
+                channel_id: "channel-1".to_string(),
+                timeout: None,
+            }),
+            timeout: None,
+            on_behalf_of: None,
+        })
+        .unwrap(),
+    });
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            gate_info.sender.as_str(),
+            &[Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::from(5_u128),
+            }],
+        ),
+        bridge_msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InsufficientFee { .. }));
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            gate_info.sender.as_str(),
+            &[Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::from(10_u128),
+            }],
+        ),
+        bridge_msg,
+    )
+    .unwrap();
+
+    match &res.messages.last().unwrap().msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_binary::<GateExecuteMsg>(msg).unwrap() {
+                GateExecuteMsg::SendRequests { timeout, .. } => assert_eq!(timeout, Some(100)),
+                _ => panic!("Unexpected gate msg"),
+            }
+        }
+        _ => panic!("Unexpected msg"),
+    }
+
+    // REMOVING THE LAST REMOTE CONTRACT REMOVES THE CONFIG
+
+    let msg = ExecuteMsg::GateRemovePermission {
+        contract: "remote_market".to_string(),
+        chain: remote_chain.clone(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    assert_gate_permission(&res, vec![]);
+
+    let msg = QueryMsg::ChainConfig {
+        chain: remote_chain,
+    };
+
+    let config: ChainConfig = from_binary(&query(deps.as_ref(), env, msg).unwrap()).unwrap();
+
+    assert_eq!(config, ChainConfig::default());
+}
+
+#[test]
+fn reserves() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    let remote_chain = "injective".to_string();
+
+    // Lowercase denoms are valid addresses for `MockApi`
+    let luna = |amount: u128| Coin {
+        denom: "uLuna".to_string(),
+        amount: Uint128::from(amount),
+    };
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msgs = vec![
+        ExecuteMsg::RegisterGate {
+            contract: gate_info.sender.clone(),
+        },
+        ExecuteMsg::GateSetPermission {
+            contract: "remote_market".to_string(),
+            chain: remote_chain.clone(),
+        },
+    ];
+
+    for msg in msgs {
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    // SET THE BRIDGE FEE
+
+    let config_msg = |bridge_fee_denom: &str| ExecuteMsg::SetChainConfig {
+        chain: remote_chain.clone(),
+        timeout: None,
+        fee_per_request: Some(luna(10)),
+        bridge_fee: Some(Coin {
+            denom: bridge_fee_denom.to_string(),
+            amount: Uint128::from(5_u128),
+        }),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        config_msg("uAtom"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::FeeDenomMismatch {}));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        config_msg("uLuna"),
+    )
+    .unwrap();
+
+    let msg = QueryMsg::BridgeFee {
+        chain: remote_chain.clone(),
+        cw20_collaterals: 2,
+    };
+
+    let res: BridgeFeeResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+
+    assert_eq!(res.fee, Some(luna(35)));
+    assert_eq!(res.protocol_fee, Some(luna(5)));
+
+    // BRIDGE A NATIVE POSITION
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            user_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(100_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap();
+
+    let bridge_msg = ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+        sender: user_info.sender,
+        msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+            to_remote_addr: "remote000".to_string(),
+            chain: remote_chain,
+            remote_contract: None,
+            native_info: Some(msgs::NativeInfo {
+                path_middle_forward: vec![],
+                dest_denom: "ibc/uatom".to_string(),
+                channel_id: "channel-1".to_string(),
+                timeout: None,
+            }),
+            timeout: None,
+            on_behalf_of: None,
+        })
+        .unwrap(),
+    });
+
+    let gate_with_fee = |amount: u128| mock_info(gate_info.sender.as_str(), &[luna(amount)]);
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_with_fee(14),
+        bridge_msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InsufficientFee { expected } if expected == luna(15)));
+
+    let res = execute(deps.as_mut(), env.clone(), gate_with_fee(15), bridge_msg).unwrap();
+
+    // Only the gate fee is forwarded to the gate
+
+    match &res.messages.last().unwrap().msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) => {
+            assert!(funds.contains(&luna(10)))
+        }
+        _ => panic!("Unexpected msg"),
+    }
+
+    let reserve = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| -> Uint128 {
+        from_binary(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Reserve {
+                    denom: "uLuna".to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(reserve(&deps), Uint128::from(5_u128));
+
+    // WITHDRAW RESERVES
+
+    let withdraw_msg = |amount: u128| ExecuteMsg::WithdrawReserves {
+        denom: "uLuna".to_string(),
+        amount: Uint128::from(amount),
+        recipient: "recipient000".to_string(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        withdraw_msg(3),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        withdraw_msg(6),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InsufficientReserves { .. }));
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info, withdraw_msg(3)).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "recipient000".to_string(),
+            amount: vec![luna(3)],
+        })
+    );
+    assert_eq!(reserve(&deps), Uint128::from(2_u128));
+}
+
+fn fee_coin(amount: u128) -> Option<Coin> {
+    (amount > 0).then(|| Coin {
+        denom: "uluna".to_string(),
+        amount: Uint128::from(amount),
+    })
+}
+
+#[test]
+fn interest() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    register_oracle(&mut deps, &owner_info, &["uAtom"]);
+
+    let interest_config = |reserve_factor: u64| ExecuteMsg::SetInterestConfig {
+        borrow_rate: Decimal::percent(10),
+        reserve_factor: Decimal::percent(reserve_factor),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        interest_config(20),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        interest_config(150),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidReserveFactor { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        interest_config(20),
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            user_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(1000_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(1000_u128),
+        on_behalf_of: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    // ONE YEAR LATER
+
+    let mut env = env;
+    env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR);
+
+    let position = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        from_binary::<Position>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Position {
+                    user: user_info.sender.clone(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(position(&deps).loan, Uint128::from(1100_u128));
+
+    let health: HealthResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Health {
+                user: user_info.sender.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(health.debt_value, Uint128::from(1100_u128));
+
+    // The interest is accrued on the next change of the position
+
+    let msg = ExecuteMsg::RepayLoan {
+        amount: Some(Uint128::from(100_u128)),
+        on_behalf_of: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        event_attributes(&res.events[0]),
+        vec![
+            ("action", "accrue_interest"),
+            ("user", "user000"),
+            ("denom", LOAN_DENOM),
+            ("amount", "100"),
+            ("reserve", "20")
+        ]
+    );
+    assert_eq!(position(&deps).loan, Uint128::from(1000_u128));
+    assert_eq!(
+        RESERVES
+            .load(&deps.storage, LOAN_DENOM.to_string())
+            .unwrap(),
+        Uint128::from(20_u128)
+    );
+
+    let msg = ExecuteMsg::WithdrawReserves {
+        denom: LOAN_DENOM.to_string(),
+        amount: Uint128::from(20_u128),
+        recipient: "owner000".to_string(),
+    };
+
+    let err = execute(deps.as_mut(), env, owner_info, msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::LoanReservesNotWithdrawable {}));
+}
+
+#[test]
+fn health() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { msg, .. } => match from_binary(msg).unwrap() {
+            oracle::msgs::QueryMsg::Price { asset, .. } => {
+                let price = match asset.as_str() {
+                    "atom" => Decimal::from_ratio(10_u128, 1_u128),
+                    "usd" => Decimal::one(),
+                    _ => panic!("Unexpected asset"),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&price).unwrap()))
+            }
+            _ => panic!("Unexpected query"),
+        },
+        _ => panic!("Unexpected query"),
+    });
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    // REGISTER ORACLE AND COLLATERAL
+
+    let msg = ExecuteMsg::RegisterOracle {
+        contract: Addr::unchecked("oracle_contract"),
+        loan_asset: "usd".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterCollateral {
+        denom: "uAtom".to_string(),
+        oracle_asset: "atom".to_string(),
+        ltv: Decimal::from_ratio(3_u128, 2_u128),
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidLtv { .. }));
+
+    let msg = ExecuteMsg::RegisterCollateral {
+        denom: "uAtom".to_string(),
+        oracle_asset: "atom".to_string(),
+        ltv: Decimal::percent(50),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    // DEPOSIT AND BORROW
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            user_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(100_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(200_u128),
+        on_behalf_of: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    let health = |msg: QueryMsg| -> HealthResponse {
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+    };
+
+    // HEALTH
+
+    assert_eq!(
+        health(QueryMsg::Health {
+            user: user_info.sender.clone(),
+        }),
+        HealthResponse {
+            collateral_value: Uint128::from(1000_u128),
+            borrow_limit: Uint128::from(500_u128),
+            liquidation_limit: Uint128::from(500_u128),
+            debt_value: Uint128::from(200_u128),
+            ltv: Some(Decimal::percent(20)),
+            max_borrowable: Uint128::from(300_u128),
+        }
+    );
+
+    // SIMULATE BORROW
+
+    assert_eq!(
+        health(QueryMsg::SimulateBorrow {
+            user: user_info.sender.clone(),
+            amount: Uint128::from(100_u128),
+        }),
+        HealthResponse {
+            collateral_value: Uint128::from(1000_u128),
+            borrow_limit: Uint128::from(500_u128),
+            liquidation_limit: Uint128::from(500_u128),
+            debt_value: Uint128::from(300_u128),
+            ltv: Some(Decimal::percent(30)),
+            max_borrowable: Uint128::from(200_u128),
+        }
+    );
+
+    // SIMULATE WITHDRAW
+
+    assert_eq!(
+        health(QueryMsg::SimulateWithdraw {
+            user: user_info.sender.clone(),
+            denom: "uAtom".to_string(),
+            amount: Some(Uint128::from(50_u128)),
+        }),
+        HealthResponse {
+            collateral_value: Uint128::from(500_u128),
+            borrow_limit: Uint128::from(250_u128),
+            liquidation_limit: Uint128::from(250_u128),
+            debt_value: Uint128::from(200_u128),
+            ltv: Some(Decimal::percent(40)),
+            max_borrowable: Uint128::from(50_u128),
+        }
+    );
+
+    assert_eq!(
+        health(QueryMsg::SimulateWithdraw {
+            user: user_info.sender.clone(),
+            denom: "uAtom".to_string(),
+            amount: None,
+        })
+        .ltv,
+        None
+    );
+
+    // SIMULATIONS DON'T CHANGE THE POSITION
+
+    let position: Position = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Position {
+                user: user_info.sender.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(position.loan, Uint128::from(200_u128));
+    assert_eq!(position.collaterals["uAtom"], Uint128::from(100_u128));
+
+    // WITHDRAW UP TO THE BORROW LIMIT
+
+    let withdraw = |amount: u128| ExecuteMsg::Withdraw {
+        denom: "uAtom".to_string(),
+        amount: Some(Uint128::from(amount)),
+        on_behalf_of: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), withdraw(60)).unwrap();
+
+    let err = execute(deps.as_mut(), env.clone(), user_info.clone(), withdraw(1)).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        ContractError::BorrowLimitExceeded {
+            debt_value: Uint128::from(200_u128),
+            borrow_limit: Uint128::from(195_u128),
+        }
+        .to_string()
+    );
+
+    // A TINY LOAN PRICE OVERFLOWING THE MAX BORROWABLE FAILS WITHOUT PANICKING
+
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { msg, .. } => match from_binary(msg).unwrap() {
+            oracle::msgs::QueryMsg::Price { asset, .. } => {
+                let price = match asset.as_str() {
+                    "atom" => Decimal::from_ratio(10_u128.pow(20), 1_u128),
+                    "usd" => Decimal::from_atomics(1_u128, 18).unwrap(),
+                    _ => panic!("Unexpected asset"),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&price).unwrap()))
+            }
+            _ => panic!("Unexpected query"),
+        },
+        _ => panic!("Unexpected query"),
+    });
+
+    let err = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Health {
+            user: user_info.sender,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::CheckedMultiplyRatio(_)));
+}
+
+#[test]
+fn categories() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { msg, .. } => match from_binary(msg).unwrap() {
+            oracle::msgs::QueryMsg::Price { asset, .. } => {
+                let price = match asset.as_str() {
+                    "atom" | "statom" | "osmo" => Decimal::from_ratio(10_u128, 1_u128),
+                    "usd" => Decimal::one(),
+                    _ => panic!("Unexpected asset"),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&price).unwrap()))
+            }
+            _ => panic!("Unexpected query"),
+        },
+        _ => panic!("Unexpected query"),
+    });
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msgs = vec![
+        ExecuteMsg::RegisterGate {
+            contract: gate_info.sender.clone(),
+        },
+        ExecuteMsg::GateSetPermission {
+            contract: "remote_market".to_string(),
+            chain: "injective".to_string(),
+        },
+        ExecuteMsg::RegisterOracle {
+            contract: Addr::unchecked("oracle_contract"),
+            loan_asset: "usd".to_string(),
+        },
+        ExecuteMsg::RegisterCollateral {
+            denom: "uAtom".to_string(),
+            oracle_asset: "atom".to_string(),
+            ltv: Decimal::percent(50),
+        },
+        ExecuteMsg::RegisterCollateral {
+            denom: "stAtom".to_string(),
+            oracle_asset: "statom".to_string(),
+            ltv: Decimal::percent(40),
+        },
+        ExecuteMsg::RegisterCollateral {
+            denom: "uOsmo".to_string(),
+            oracle_asset: "osmo".to_string(),
+            ltv: Decimal::percent(50),
+        },
+    ];
+
+    for msg in msgs {
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    // REGISTER CATEGORY
+
+    let register_category =
+        |liquidation_threshold: Decimal, collaterals: Vec<&str>| ExecuteMsg::RegisterCategory {
+            name: "atom".to_string(),
+            collaterals: collaterals.into_iter().map(|c| c.to_string()).collect(),
+            ltv: Decimal::percent(90),
+            liquidation_threshold,
+        };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        register_category(Decimal::percent(95), vec!["uAtom", "stAtom"]),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        register_category(Decimal::percent(85), vec!["uAtom", "stAtom"]),
+    )
+    .unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::InvalidLiquidationThreshold { .. }
+    ));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        register_category(Decimal::percent(95), vec!["uAtom", "uLuna"]),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::CollateralNotFound {}));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info,
+        register_category(Decimal::percent(95), vec!["uAtom", "stAtom"]),
+    )
+    .unwrap();
+
+    let category: CollateralCategory = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Category {
+                name: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(category.ltv, Decimal::percent(90));
+    assert_eq!(category.liquidation_threshold, Decimal::percent(95));
+
+    // OPT INTO THE CATEGORY
+
+    let deposit = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, denom: &str| {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(
+                user_info.sender.as_str(),
+                &[Coin {
+                    denom: denom.to_string(),
+                    amount: Uint128::from(100_u128),
+                }],
+            ),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+    };
+
+    let set_category = |category: Option<&str>| ExecuteMsg::SetPositionCategory {
+        category: category.map(|c| c.to_string()),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        set_category(Some("atom")),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::UserNotFound {}));
+
+    deposit(&mut deps, "uAtom");
+    deposit(&mut deps, "stAtom");
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        set_category(Some("btc")),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::CategoryNotFound { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        set_category(Some("atom")),
+    )
+    .unwrap();
+
+    let health: HealthResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Health {
+                user: user_info.sender.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(health.borrow_limit, Uint128::from(1800_u128));
+    assert_eq!(health.liquidation_limit, Uint128::from(1900_u128));
+
+    // BORROW OVER THE CATEGORY LIMIT
+
+    let msg = ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(1801_u128),
+        on_behalf_of: None,
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        ContractError::BorrowLimitExceeded {
+            debt_value: Uint128::from(1801_u128),
+            borrow_limit: Uint128::from(1800_u128),
+        }
+        .to_string()
+    );
+
+    // BORROW OVER THE LIMIT OUTSIDE THE CATEGORY
+
+    let msg = ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(1500_u128),
+        on_behalf_of: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        user_info.clone(),
+        set_category(None),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        ContractError::BorrowLimitExceeded {
+            debt_value: Uint128::from(1500_u128),
+            borrow_limit: Uint128::from(900_u128),
+        }
+        .to_string()
+    );
+
+    // COLLATERALS OUTSIDE THE CATEGORY
+
+    deposit(&mut deps, "uOsmo");
+
+    let msg = ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(10_u128),
+        on_behalf_of: None,
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::CollateralNotInCategory { .. }));
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+        sender: user_info.sender.clone(),
+        msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+            to_remote_addr: "remote000".to_string(),
+            chain: "injective".to_string(),
+            remote_contract: None,
+            native_info: None,
+            timeout: None,
+            on_behalf_of: None,
+        })
+        .unwrap(),
+    });
+
+    let err = execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::CollateralNotInCategory { .. }));
+
+    // RECEIVED POSITION OUTSIDE THE CATEGORY
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::ReceivedMsg {
+        sender: "remote_market".to_string(),
+        msg: to_binary(&BridgeMsgInfo {
+            bridge_id: 1,
+            chain: "local_chain".to_string(),
+            sender: "remote000".to_string(),
+            receiver: "user001".to_string(),
+            src_position: Position {
+                loan: Uint128::zero(),
+                collaterals: HashMap::new(),
+                category: Some("atom".to_string()),
+            },
+            dest_position: Position {
+                loan: Uint128::zero(),
+                collaterals: HashMap::from([("uOsmo".to_string(), Uint128::from(5_u128))]),
+                category: Some("atom".to_string()),
+            },
+        })
+        .unwrap(),
+    });
+
+    let err = execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::CollateralNotInCategory { .. }));
+}
+
+#[test]
+fn flash_loan() {
+    let mut deps = mock_dependencies_with_balance(&[Coin {
+        denom: "uAtom".to_string(),
+        amount: Uint128::from(1000_u128),
+    }]);
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let receiver_info = mock_info("receiver000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::SetFlashLoanFee {
+        fee: Decimal::percent(1),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        receiver_info.clone(),
+        msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    let flash_loan = |amount: u128| ExecuteMsg::FlashLoan {
+        denom: "uAtom".to_string(),
+        amount: Uint128::from(amount),
+        callback: to_binary("callback").unwrap(),
+    };
+
+    // NOT ENOUGH LIQUIDITY
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        receiver_info.clone(),
+        flash_loan(1001),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InsufficientLiquidity { .. }));
+
+    // FLASH LOAN
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        receiver_info.clone(),
+        flash_loan(500),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            WasmMsg::Execute {
+                contract_addr: receiver_info.sender.to_string(),
+                msg: to_binary(&FlashLoanReceiverMsg::FlashLoanCallback {
+                    denom: "uAtom".to_string(),
+                    amount: Uint128::from(500_u128),
+                    fee: Uint128::from(5_u128),
+                    msg: to_binary("callback").unwrap(),
+                })
+                .unwrap(),
+                funds: vec![Coin {
+                    denom: "uAtom".to_string(),
+                    amount: Uint128::from(500_u128),
+                }],
+            },
+            MsgReplyID::FlashLoan.repr(),
+        )]
+    );
+
+    // NO DEPOSITS OR NESTED FLASH LOANS DURING THE CALLBACK
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            receiver_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(505_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::FlashLoanInProgress {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        receiver_info.clone(),
+        flash_loan(100),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::FlashLoanInProgress {}));
+
+    // REPLY
+
+    let callback_reply = || Reply {
+        id: MsgReplyID::FlashLoan.repr(),
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+
+    // Only the principal is returned
+    let err = reply(deps.as_mut(), env.clone(), callback_reply()).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        ContractError::FlashLoanNotRepaid {
+            expected: Uint128::from(1005_u128),
+            found: Uint128::from(1000_u128),
+        }
+        .to_string()
+    );
+
+    deps.querier.update_balance(
+        env.contract.address.clone(),
+        vec![Coin {
+            denom: "uAtom".to_string(),
+            amount: Uint128::from(1005_u128),
+        }],
+    );
+
+    reply(deps.as_mut(), env.clone(), callback_reply()).unwrap();
+
+    assert_eq!(
+        RESERVES.load(&deps.storage, "uAtom".to_string()).unwrap(),
+        Uint128::from(5_u128)
+    );
+    assert!(!FLASH_LOAN.exists(&deps.storage));
+
+    // THE COLLATERALS ARE NOT LENT
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            "user000",
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(400_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap();
+
+    deps.querier.update_balance(
+        env.contract.address.clone(),
+        vec![Coin {
+            denom: "uAtom".to_string(),
+            amount: Uint128::from(1405_u128),
+        }],
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        receiver_info.clone(),
+        flash_loan(1006),
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        ContractError::InsufficientLiquidity {
+            available: Uint128::from(1005_u128)
+        }
+        .to_string()
+    );
+
+    execute(deps.as_mut(), env.clone(), receiver_info, flash_loan(1005)).unwrap();
+}
+
+#[test]
+fn operators() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+    let user_info = mock_info("user000", &[]);
+    let vault_info = mock_info("vault000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    register_oracle(&mut deps, &owner_info, &["token000"]);
+
+    let msg = ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+        sender: user_info.sender.to_string(),
+        amount: Uint128::from(100_u128),
+        msg: to_binary(&msgs::Cw20MsgType::Deposit {}).unwrap(),
+    });
+
+    execute(deps.as_mut(), env.clone(), mock_info("token000", &[]), msg).unwrap();
+
+    // APPROVE
+
+    let coin = |denom: &str, amount: u128| Coin {
+        denom: denom.to_string(),
+        amount: Uint128::from(amount),
+    };
+
+    let approval = Approval {
+        allowances: Some(vec![coin("loan", 50), coin("token000", 30)]),
+        expires: Expiration::AtHeight(env.block.height + 10),
+    };
+
+    let msg = ExecuteMsg::ApproveOperator {
+        operator: vault_info.sender.to_string(),
+        allowances: approval.allowances.clone(),
+        expires: Some(approval.expires),
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    let approvals: Vec<(Addr, Approval)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Approvals {
+                user: user_info.sender.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(approvals, vec![(vault_info.sender.clone(), approval)]);
+
+    // BORROW, WITHDRAW AND REPAY ON BEHALF OF THE USER
+
+    let on_behalf_of = Some(user_info.sender.to_string());
+
+    let borrow = |amount: u128, on_behalf_of: Option<String>| ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(amount),
+        on_behalf_of,
+    };
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        vault_info.clone(),
+        borrow(40, on_behalf_of.clone()),
+    )
+    .unwrap();
+
+    assert_eq!(
+        event_attributes(&res.events[0]),
+        vec![
+            ("action", "borrow"),
+            ("user", "user000"),
+            ("denom", "loan"),
+            ("amount", "40"),
+            ("operator", "vault000")
+        ]
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        vault_info.clone(),
+        borrow(20, on_behalf_of.clone()),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::AllowanceExceeded { .. }));
+
+    let withdraw = |amount: u128| ExecuteMsg::Withdraw {
+        denom: "token000".to_string(),
+        amount: Some(Uint128::from(amount)),
+        on_behalf_of: Some(user_info.sender.to_string()),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), vault_info.clone(), withdraw(30)).unwrap();
+
+    // The collateral is sent to the user, not to the operator
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token000".to_string(),
+            msg: to_binary(&cw20::Cw20ExecuteMsg::Transfer {
+                recipient: user_info.sender.to_string(),
+                amount: Uint128::from(30_u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    let err = execute(deps.as_mut(), env.clone(), vault_info.clone(), withdraw(1)).unwrap_err();
+
+    assert!(matches!(err, ContractError::AllowanceExceeded { .. }));
+
+    // Repaying uses the loan allowance too
+
+    let repay = |amount: Option<u128>| ExecuteMsg::RepayLoan {
+        amount: amount.map(Uint128::from),
+        on_behalf_of: on_behalf_of.clone(),
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), vault_info.clone(), repay(None)).unwrap_err();
+
+    assert!(matches!(err, ContractError::AllowanceExceeded { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        vault_info.clone(),
+        repay(Some(10)),
+    )
+    .unwrap();
+
+    let position: Position = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Position {
+                user: user_info.sender.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(position.loan, Uint128::from(30_u128));
+    assert_eq!(position.collaterals["token000"], Uint128::from(70_u128));
+
+    let approval: Approval = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Approval {
+                user: user_info.sender.clone(),
+                operator: vault_info.sender.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        approval.allowances,
+        Some(vec![coin("loan", 0), coin("token000", 0)])
+    );
+
+    // OPERATORS WITH LIMITS CAN'T BRIDGE
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+        sender: vault_info.sender.clone(),
+        msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+            to_remote_addr: "remote000".to_string(),
+            chain: "injective".to_string(),
+            remote_contract: None,
+            native_info: None,
+            timeout: None,
+            on_behalf_of: on_behalf_of.clone(),
+        })
+        .unwrap(),
+    });
+
+    let err = execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::OperatorAllowanceLimited {}));
+
+    // OPERATORS CAN ONLY BRIDGE TO THE REMOTE ADDRESS OF THE USER
+
+    let msg = ExecuteMsg::ApproveOperator {
+        operator: vault_info.sender.to_string(),
+        allowances: None,
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    let bridge = |to_remote_addr: &str| {
+        ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+            sender: vault_info.sender.clone(),
+            msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+                to_remote_addr: to_remote_addr.to_string(),
+                chain: "injective".to_string(),
+                remote_contract: None,
+                native_info: None,
+                timeout: None,
+                on_behalf_of: on_behalf_of.clone(),
+            })
+            .unwrap(),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        bridge("remote000"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::RemoteAddrNotRegistered { .. }));
+
+    let msg = ExecuteMsg::SetRemoteAddr {
+        chain: "injective".to_string(),
+        remote_addr: Some("remote001".to_string()),
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    let remote_addr: Option<String> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RemoteAddr {
+                user: user_info.sender.clone(),
+                chain: "injective".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(remote_addr, Some("remote001".to_string()));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        bridge("remote000"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::RemoteAddrNotRegistered { .. }));
+
+    // The registered address passes the check, the chain is not registered in this test
+    let err = execute(deps.as_mut(), env.clone(), gate_info, bridge("remote001")).unwrap_err();
+
+    assert!(matches!(err, ContractError::ChainNotRegistered { .. }));
+
+    // NOT APPROVED AND EXPIRED
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        vault_info.clone(),
+        borrow(1, Some("user001".to_string())),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::OperatorNotApproved {}));
+
+    let mut expired_env = env.clone();
+    expired_env.block.height += 10;
+
+    let err = execute(
+        deps.as_mut(),
+        expired_env,
+        vault_info.clone(),
+        borrow(1, on_behalf_of.clone()),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::ApprovalExpired {}));
+
+    // REVOKE
+
+    let msg = ExecuteMsg::RevokeOperator {
+        operator: vault_info.sender.to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), user_info.clone(), msg.clone()).unwrap();
+
+    let err = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::OperatorNotApproved {}));
+
+    let err = execute(deps.as_mut(), env, vault_info, borrow(1, on_behalf_of)).unwrap_err();
+
+    assert!(matches!(err, ContractError::OperatorNotApproved {}));
+}
+
+/// Register a mock oracle that prices the loan asset `usd` at 1 and every other asset at 10,
+/// and register `collaterals` with a 50% ltv, priced by the asset with the same name.
+fn register_oracle(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    owner_info: &MessageInfo,
+    collaterals: &[&str],
+) {
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { msg, .. } => match from_binary(msg).unwrap() {
+            oracle::msgs::QueryMsg::Price { asset, .. } => {
+                let price = match asset.as_str() {
+                    "usd" => Decimal::one(),
+                    _ => Decimal::from_ratio(10_u128, 1_u128),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&price).unwrap()))
+            }
+            _ => panic!("Unexpected query"),
+        },
+        _ => panic!("Unexpected query"),
+    });
+
+    let msg = ExecuteMsg::RegisterOracle {
+        contract: Addr::unchecked("oracle_contract"),
+        loan_asset: "usd".to_string(),
+    };
+
+    execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+
+    for denom in collaterals {
+        let msg = ExecuteMsg::RegisterCollateral {
+            denom: denom.to_string(),
+            oracle_asset: denom.to_string(),
+            ltv: Decimal::percent(50),
+        };
+
+        execute(deps.as_mut(), mock_env(), owner_info.clone(), msg).unwrap();
+    }
+}
+
+fn event_attributes(event: &Event) -> Vec<(&str, &str)> {
+    event
+        .attributes
+        .iter()
+        .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+        .collect()
+}
+
+#[test]
+fn events() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+    let user_info = mock_info("user000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_market".to_string(),
+        chain: "injective".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    register_oracle(&mut deps, &owner_info, &["uAtom"]);
+
+    // DEPOSIT
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(
+            user_info.sender.as_str(),
+            &[Coin {
+                denom: "uAtom".to_string(),
+                amount: Uint128::from(100_u128),
+            }],
+        ),
+        ExecuteMsg::Deposit {},
+    )
+    .unwrap();
+
+    assert_eq!(res.events[0].ty, "market");
+    assert_eq!(
+        event_attributes(&res.events[0]),
+        vec![
+            ("action", "deposit"),
+            ("user", "user000"),
+            ("denom", "uAtom"),
+            ("amount", "100")
+        ]
+    );
+
+    // BORROW AND REPAY
+
+    let msg = ExecuteMsg::IncreaseLoan {
+        amount: Uint128::from(50_u128),
+        on_behalf_of: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        event_attributes(&res.events[0]),
+        vec![
+            ("action", "borrow"),
+            ("user", "user000"),
+            ("denom", "loan"),
+            ("amount", "50")
+        ]
+    );
+
+    let msg = ExecuteMsg::RepayLoan {
+        amount: Some(Uint128::from(20_u128)),
+        on_behalf_of: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), user_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        event_attributes(&res.events[0]),
+        vec![
+            ("action", "repay"),
+            ("user", "user000"),
+            ("denom", "loan"),
+            ("amount", "20")
+        ]
+    );
+
+    // BRIDGE OUT
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+        sender: user_info.sender.clone(),
+        msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+            to_remote_addr: "remote000".to_string(),
+            chain: "injective".to_string(),
+            remote_contract: None,
+            native_info: Some(msgs::NativeInfo {
+                path_middle_forward: vec![],
+                dest_denom: "ibc/uatom".to_string(),
+                channel_id: "channel-1".to_string(),
+                timeout: None,
+            }),
+            timeout: None,
+            on_behalf_of: None,
+        })
+        .unwrap(),
+    });
+
+    let res = execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    let bridge_out: Vec<Vec<(&str, &str)>> = res.events.iter().map(event_attributes).collect();
+
+    assert_eq!(
+        bridge_out,
+        vec![
+            vec![
+                ("action", "bridge_out"),
+                ("user", "user000"),
+                ("denom", "uAtom"),
+                ("amount", "100"),
+                ("dest_chain", "injective"),
+                ("bridge_id", "1")
+            ],
+            vec![
+                ("action", "bridge_out"),
+                ("user", "user000"),
+                ("denom", "loan"),
+                ("amount", "30"),
+                ("dest_chain", "injective"),
+                ("bridge_id", "1")
+            ]
+        ]
+    );
+
+    // BRIDGE REVERT
+
+    let request = match &res.messages.last().unwrap().msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            match from_binary::<GateExecuteMsg>(msg).unwrap() {
+                GateExecuteMsg::SendRequests { requests, .. } => requests[0].clone(),
+                _ => panic!("Unexpected gate msg"),
+            }
+        }
+        _ => panic!("Unexpected msg"),
+    };
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::RequestFailed { request });
+
+    let res = execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    let bridge_revert: Vec<Vec<(&str, &str)>> = res.events.iter().map(event_attributes).collect();
+
+    assert_eq!(
+        bridge_revert,
+        bridge_out
+            .iter()
+            .map(|attributes| {
+                let mut attributes = attributes.clone();
+                attributes[0] = ("action", "bridge_revert");
+                attributes
+            })
+            .collect::<Vec<_>>()
+    );
+
+    // BRIDGE IN FROM A NOT REGISTERED CONTRACT
+
+    let bridge_in = |sender: &str| {
+        ExecuteMsg::ReceiveGateMsg(GateMsg::ReceivedMsg {
+            sender: sender.to_string(),
+            msg: to_binary(&BridgeMsgInfo {
+                bridge_id: 7,
+                chain: "local_chain".to_string(),
+                sender: "remote_user".to_string(),
+                receiver: "user001".to_string(),
+                src_position: Position {
+                    loan: Uint128::zero(),
+                    collaterals: HashMap::new(),
+                    category: None,
+                },
+                dest_position: Position {
+                    loan: Uint128::zero(),
+                    collaterals: HashMap::from([("token_1".to_string(), Uint128::from(5_u128))]),
+                    category: None,
+                },
+            })
+            .unwrap(),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        bridge_in("unknown_contract"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let res = execute(deps.as_mut(), env, gate_info, bridge_in("remote_market")).unwrap();
+
+    assert_eq!(
+        event_attributes(&res.events[0]),
+        vec![
+            ("action", "bridge_in"),
+            ("user", "user001"),
+            ("denom", "token_1"),
+            ("amount", "5"),
+            ("src_chain", "injective"),
+            ("bridge_id", "7")
+        ]
+    );
+}
+
+#[test]
+fn migrate_chains_contract() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+
+    // Entries saved with a single remote contract, before the lists
+
+    let legacy: Map<String, String> = Map::new("chains_contracts");
+
+    legacy
+        .save(
+            deps.as_mut().storage,
+            "injective".to_string(),
+            &"remote_market".to_string(),
+        )
+        .unwrap();
+
+    CHAINS_CONTRACT
+        .save(
+            deps.as_mut().storage,
+            "osmosis".to_string(),
+            &vec!["remote_market_0".to_string(), "remote_market_1".to_string()],
+        )
+        .unwrap();
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    let remote_contracts = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, chain: &str| {
+        from_binary::<Vec<String>>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::RemoteContracts {
+                    chain: chain.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert_eq!(
+        remote_contracts(&deps, "injective"),
+        vec!["remote_market".to_string()]
+    );
+    assert_eq!(
+        remote_contracts(&deps, "osmosis"),
+        vec!["remote_market_0".to_string(), "remote_market_1".to_string()]
+    );
+
+    // Migrating again is a no-op
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    assert_eq!(
+        remote_contracts(&deps, "injective"),
+        vec!["remote_market".to_string()]
+    );
+}
+
+#[test]
+fn migrate_total_deposits() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+
+    // Positions saved before the deposits were tracked
+
+    for (user, amount) in [("user000", 100_u128), ("user001", 250)] {
+        POSITIONS
+            .save(
+                deps.as_mut().storage,
+                Addr::unchecked(user),
+                &Position {
+                    loan: Uint128::zero(),
+                    collaterals: HashMap::from([
+                        ("uAtom".to_string(), Uint128::from(amount)),
+                        ("token000".to_string(), Uint128::from(amount * 2)),
+                    ]),
+                    category: None,
+                },
+            )
+            .unwrap();
+    }
+
+    // Running the migration twice doesn't count the deposits twice
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+    migrate(deps.as_mut(), env, MigrateMsg {}).unwrap();
+
+    assert_eq!(
+        TOTAL_DEPOSITS
+            .load(&deps.storage, "uAtom".to_string())
+            .unwrap(),
+        Uint128::from(350_u128)
+    );
+    assert_eq!(
+        TOTAL_DEPOSITS
+            .load(&deps.storage, "token000".to_string())
+            .unwrap(),
+        Uint128::from(700_u128)
+    );
+}
+
+proptest! {
+    #[test]
+    fn split_bridge_fee_preserve_amount(
+        cw20_collaterals in 0_u64..20,
+        fee in 0_u128..1_000_000_000,
+        fee_per_request in prop::option::of(1_u128..1_000),
+    ) {
+        let chain_config = ChainConfig {
+            timeout: None,
+            fee_per_request: fee_per_request.map(|amount| Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::from(amount),
+            }),
+            bridge_fee: None,
+        };
+
+        let requests = cw20_collaterals as u128 + 1;
+
+        let amount = |coin: Option<Coin>| coin.map(|coin| coin.amount.u128()).unwrap_or_default();
+
+        match split_bridge_fee(fee_coin(fee), cw20_collaterals, &chain_config) {
+            Ok(split) => {
+                let per_cw20 = amount(split.per_cw20);
+
+                prop_assert_eq!(per_cw20 * cw20_collaterals as u128 + amount(split.market), fee);
+
+                match fee_per_request {
+                    Some(fee_per_request) => prop_assert_eq!(per_cw20, fee_per_request),
+                    None => prop_assert!(fee == 0 || per_cw20 == fee / requests),
+                }
+            }
+            Err(ContractError::InsufficientFee { expected }) => {
+                prop_assert!(fee < expected.amount.u128());
+                prop_assert_eq!(
+                    expected.amount.u128(),
+                    requests * fee_per_request.unwrap_or(1)
+                );
+            }
+            Err(err) => prop_assert!(false, "Unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn bridge_position_fee_split(
+        cw20_collaterals in 0_usize..5,
+        has_native in any::<bool>(),
+        with_native_info in any::<bool>(),
+        fee in 0_u128..1_000_000,
+    ) {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner_info = mock_info("onwer000", &[]);
+        let gate_info = mock_info("gate_contract", &[]);
+        let user_info = mock_info("user000", &[]);
+
+        let remote_chain = "injective".to_string();
+
+        deps.querier = MockQuerier::new(&[]);
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } => SystemResult::Ok(ContractResult::Ok(
+                to_binary(&format!("remote_{contract_addr}")).unwrap(),
+            )),
+            _ => panic!("Unexpected query"),
+        });
+
+        instantiate(deps.as_mut(), env.clone(), owner_info.clone(), InstantiateMsg {}).unwrap();
+
+        let msg = ExecuteMsg::RegisterGate {
+            contract: gate_info.sender.clone(),
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::GateSetPermission {
+            contract: "remote_market".to_string(),
+            chain: remote_chain.clone(),
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+        // BUILD THE POSITION
+
+        POSITIONS.save(deps.as_mut().storage, user_info.sender.clone(), &Position {
+            loan: Uint128::from(10_u128),
+            collaterals: HashMap::new(),
+            category: None,
+        }).unwrap();
+
+        for i in 0..cw20_collaterals {
+            let msg = ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: user_info.sender.to_string(),
+                amount: Uint128::from(100_u128),
+                msg: to_binary(&msgs::Cw20MsgType::Deposit {}).unwrap(),
+            });
+
+            execute(deps.as_mut(), env.clone(), mock_info(&format!("token{i}"), &[]), msg).unwrap();
+        }
+
+        if has_native {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(
+                    user_info.sender.as_str(),
+                    &[Coin {
+                        denom: "uAtom".to_string(),
+                        amount: Uint128::from(100_u128),
+                    }],
+                ),
+                ExecuteMsg::Deposit {},
+            )
+            .unwrap();
+        }
+
+        // BRIDGE
+
+        let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::CollectRequests {
+            sender: user_info.sender,
+            msg: to_binary(&GateCollectMsgsAllowed::BridgePosition {
+                to_remote_addr: "remote000".to_string(),
+                chain: remote_chain,
+                remote_contract: None,
+                native_info: with_native_info.then(|| msgs::NativeInfo {
+                    path_middle_forward: vec![],
+                    dest_denom: "ibc/uatom".to_string(),
+                    channel_id: "channel-1".to_string(),
+                    timeout: None,
+                }),
+                timeout: None,
+                on_behalf_of: None,
+            })
+            .unwrap(),
+        });
+
+        let funds: Vec<Coin> = fee_coin(fee).into_iter().collect();
+
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info(gate_info.sender.as_str(), &funds),
+            msg,
+        );
+
+        match res {
+            Ok(res) => {
+                prop_assert_eq!(has_native, with_native_info);
+                prop_assert_eq!(res.messages.len(), cw20_collaterals + 1);
+
+                let mut sent_fee = 0_u128;
+                let mut sent_native = 0_u128;
+
+                for msg in res.messages {
+                    if let CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) = msg.msg {
+                        for coin in funds {
+                            prop_assert!(!coin.amount.is_zero());
+
+                            match coin.denom.as_str() {
+                                "uluna" => sent_fee += coin.amount.u128(),
+                                "uAtom" => sent_native += coin.amount.u128(),
+                                _ => prop_assert!(false, "Unexpected denom {}", coin.denom),
+                            }
+                        }
+                    }
+                }
+
+                prop_assert_eq!(sent_fee, fee);
+                prop_assert_eq!(sent_native, if has_native { 100 } else { 0 });
+            }
+            Err(ContractError::NativeInfoRequired {}) => {
+                prop_assert!(has_native && !with_native_info)
+            }
+            Err(ContractError::NativeCollateralNotFound {}) => {
+                prop_assert!(!has_native && with_native_info)
+            }
+            Err(ContractError::InsufficientFee { .. }) => {
+                prop_assert!(fee > 0 && fee < cw20_collaterals as u128 + 1)
+            }
+            Err(err) => prop_assert!(false, "Unexpected error: {}", err),
+        }
+    }
+}
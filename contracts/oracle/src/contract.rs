@@ -1,17 +1,30 @@
-use std::cmp::min;
+use std::{cmp::min, collections::BTreeMap};
 
+use cosmwasm_schema::cw_serde;
 #[cfg(not(feature = "library"))]
-use cosmwasm_std::{entry_point, DepsMut, Env, MessageInfo, Response};
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Addr, Attribute, Binary, Coin, CosmosMsg, Decimal, Deps, Order,
-    QueryRequest, StdError, StdResult, Storage, WasmMsg, WasmQuery,
+    from_binary, to_binary, Addr, Attribute, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps,
+    DepsMut, Env, MessageInfo, Order, QueryRequest, Reply, Response, StdError, StdResult, Storage,
+    SubMsg, Timestamp, Uint128, WasmMsg, WasmQuery,
 };
-use cw_storage_plus::Bound;
+use cw_storage_plus::{Bound, Map};
 
 use crate::{
     errors::ContractError,
-    msgs::{ExecuteMsg, InstantiateMsg, QueryMsg},
-    state::{Asset, ASSETS, CHAINS_CONTRACT, GATE, OWNER},
+    msgs::{
+        ChainResponse, CrossRateResponse, ExecuteMsg, GuardResponse, InstantiateMsg,
+        LegacyQueryMsg, MigrateMsg, MsgReplyID, OracleGateMsg, PathStep, PriceReceiverMsg,
+        PriceResponse, PriceSelector, QueryMsg, RequestCallback,
+    },
+    state::{
+        Aggregation, Asset, AssetMetadata, FeedConfig, Frozen, GuardAction, PendingRequest,
+        PermissionMode, PriceCallback, PriceGuard, PriceInfo, PriceSource, RefreshFees,
+        RequestStatus, Submission, Subscription, SubscriptionKey, ASSETS, CHAINS_CONTRACT,
+        CHAINS_LEGACY_CONTRACT, CHAINS_PERMISSION, GATE, HISTORY, HISTORY_COUNT, HISTORY_SIZE,
+        MAX_MIN_INTERVAL, MAX_PATH_LENGTH, OWNER, PAIRS, PENDING_REQUESTS, REFRESH_FEES,
+        REMOTE_PRICES, REQUEST_ID, SUBMISSIONS, SUBSCRIPTIONS,
+    },
 };
 
 use gate_pkg::{ExecuteMsg as GateExecuteMsg, GateMsg, GateQueryResponse, GateRequest, Permission};
@@ -37,34 +50,240 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::RegisterAsset { asset, feeder } => {
-            run_regiser_asset(deps, info.sender, asset, feeder)
+        ExecuteMsg::RegisterAsset {
+            asset,
+            feeders,
+            feed_config,
+            metadata,
+        } => run_regiser_asset(deps, info.sender, asset, feeders, feed_config, metadata),
+        ExecuteMsg::UpdateFeeder {
+            asset,
+            old_feeder,
+            new_feeder,
+        } => run_update_feeder(deps, info.sender, asset, old_feeder, new_feeder),
+        ExecuteMsg::SetAssetMetadata { asset, metadata } => {
+            run_set_asset_metadata(deps, info.sender, asset, metadata)
+        }
+        ExecuteMsg::RemoveAsset { asset } => run_remove_asset(deps, info.sender, asset),
+        ExecuteMsg::SetFeedConfig { asset, feed_config } => {
+            run_set_feed_config(deps, info.sender, asset, feed_config)
+        }
+        ExecuteMsg::SetPriceGuard { asset, guard } => {
+            run_set_price_guard(deps, info.sender, asset, guard)
+        }
+        ExecuteMsg::ResolveFrozen { asset, price } => {
+            run_resolve_frozen(deps, env, info.sender, asset, price)
+        }
+        ExecuteMsg::FeedPrice { asset, price } => {
+            run_feed_price(deps, env, info.sender, asset, price)
         }
-        ExecuteMsg::FeedPrice { asset, price } => run_feed_price(deps, info.sender, asset, price),
         ExecuteMsg::FeedRemotePrice { asset, chain } => {
-            run_feed_remote_price(deps, info.sender, info.funds, asset, chain)
+            run_feed_remote_price(deps, env, info.sender, info.funds, asset, chain)
+        }
+        ExecuteMsg::FeedRemotePrices { assets, chain } => {
+            run_feed_remote_prices(deps, env, info.sender, info.funds, assets, vec![chain])
+        }
+        ExecuteMsg::FeedRemotePricesMultiChain { assets, chains } => {
+            run_feed_remote_prices(deps, env, info.sender, info.funds, assets, chains)
+        }
+        ExecuteMsg::PushPrices { assets, chains } => {
+            run_push_prices(deps, info.funds, assets, chains)
         }
+        ExecuteMsg::RequestRemotePrice {
+            asset,
+            chain,
+            callback,
+        } => run_request_remote_price(deps, env, info.sender, info.funds, asset, chain, callback),
+        ExecuteMsg::RetryRequest { request_id } => {
+            run_retry_request(deps, env, info.funds, request_id)
+        }
+        ExecuteMsg::SetRefreshFees { fees } => run_set_refresh_fees(deps, info.sender, fees),
+        ExecuteMsg::Subscribe {
+            asset,
+            chain,
+            min_interval,
+        } => run_subscribe(deps, info.sender, info.funds, asset, chain, min_interval),
+        ExecuteMsg::FundSubscription {
+            asset,
+            chain,
+            subscriber,
+        } => run_fund_subscription(deps, info.funds, asset, chain, subscriber),
+        ExecuteMsg::Unsubscribe {
+            asset,
+            chain,
+            subscriber,
+        } => run_unsubscribe(deps, info.sender, asset, chain, subscriber),
+        ExecuteMsg::RefreshDue {} => run_refresh_due(deps, env, info.sender),
         ExecuteMsg::RegisterGate { contract } => run_register_gate(deps, info.sender, contract),
         ExecuteMsg::GateSetPermission { contract, chain } => {
             run_gate_set_permission(deps, info.sender, contract, chain)
         }
-        ExecuteMsg::ReceiveGateMsg(msg) => gate_receive_msg(deps, info, msg),
+        ExecuteMsg::GateRemovePermission { contract, chain } => {
+            run_gate_remove_permission(deps, info.sender, contract, chain)
+        }
+        ExecuteMsg::GateSetPermissionMode { chain, mode } => {
+            run_gate_set_permission_mode(deps, info.sender, chain, mode)
+        }
+        ExecuteMsg::SetLegacyContract {
+            contract,
+            chain,
+            legacy,
+        } => run_set_legacy_contract(deps, info.sender, contract, chain, legacy),
+        ExecuteMsg::RemoveChain { chain } => run_remove_chain(deps, info.sender, chain),
+        ExecuteMsg::ReceiveGateMsg(msg) => gate_receive_msg(deps, env, info, msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    let binary = match msg {
+        QueryMsg::Price {
+            asset,
+            max_age,
+            selector,
+        } => to_binary(&qy_price(deps, env, asset, max_age, selector)?),
+        QueryMsg::CrossRate {
+            base,
+            quote,
+            max_age,
+            max_path_length,
+        } => to_binary(&qy_cross_rate(
+            deps,
+            env,
+            base,
+            quote,
+            max_age,
+            max_path_length,
+        )?),
+        QueryMsg::PriceWithMeta { asset } => to_binary(&qy_price_with_meta(deps, asset)?),
+        QueryMsg::PricesByList { assets } => to_binary(&qy_prices_by_list(deps, assets)?),
+        QueryMsg::RemotePrices { asset } => to_binary(&qy_remote_prices(deps, asset)?),
+        QueryMsg::AssetInfo { asset } => to_binary(&ASSETS.load(deps.storage, asset)?),
+        QueryMsg::Assets { start_after, limit } => to_binary(&qy_assets(deps, start_after, limit)?),
+        QueryMsg::Guard { asset } => to_binary(&qy_guard(deps, asset)?),
+        QueryMsg::Feeders { asset } => to_binary(&qy_feeders(deps, asset)?),
+        QueryMsg::Twap {
+            asset,
+            window_seconds,
+        } => to_binary(&qy_twap(deps, env, asset, window_seconds)?),
+        QueryMsg::History {
+            asset,
+            start_after,
+            limit,
+        } => to_binary(&qy_history(deps, asset, start_after, limit)?),
+        QueryMsg::Chain { chain } => to_binary(&qy_chain(deps, chain)?),
+        QueryMsg::Chains { start_after, limit } => to_binary(&qy_chains(deps, start_after, limit)?),
+        QueryMsg::RefreshFees {} => to_binary(&REFRESH_FEES.may_load(deps.storage)?),
+        QueryMsg::Subscriptions { start_after, limit } => {
+            to_binary(&qy_subscriptions(deps, start_after, limit)?)
+        }
+        QueryMsg::PendingRequests { start_after, limit } => {
+            to_binary(&qy_pending_requests(deps, start_after, limit)?)
+        }
+        QueryMsg::Prices { start_after, limit } => to_binary(&qy_prices(deps, start_after, limit)?),
+    }?;
+
+    Ok(binary)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(_deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match MsgReplyID::from_repr(reply.id) {
+        Some(MsgReplyID::PriceCallback) => Ok(Response::new()
+            .add_attribute("action", "price_callback_failed")
+            .add_attribute(
+                "error",
+                reply.result.into_result().err().unwrap_or_default(),
+            )),
+        None => Err(ContractError::Std(StdError::generic_err(format!(
+            "invalid reply id {}",
+            reply.id
+        )))),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Price { asset } => to_binary(&qy_price(deps, asset).unwrap()),
-        QueryMsg::Prices { start_after, limit } => {
-            to_binary(&qy_prices(deps, start_after, limit).unwrap())
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    migrate_assets(deps.storage, &env)?;
+    migrate_chains_contract(deps.storage)?;
+
+    Ok(Response::new())
+}
+
+/// `ASSETS` used to store a single feeder and the bare price of every asset.
+/// Rewrite every legacy entry with the default configs. The price is kept as set by the owner at the migration.
+/// The entries already migrated are skipped.
+fn migrate_assets(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    #[cw_serde]
+    struct LegacyAsset {
+        feeder: Addr,
+        price: Option<Decimal>,
+    }
+
+    const LEGACY_ASSETS: Map<String, LegacyAsset> = Map::new("assets");
+
+    // The values can't be deserialized before the migration, only the raw keys are read
+    let assets = ASSETS
+        .keys_raw(storage, None, None, Order::Ascending)
+        .map(|key| String::from_utf8(key).map_err(StdError::from))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    for asset in assets {
+        if ASSETS.load(storage, asset.clone()).is_ok() {
+            continue;
+        }
+
+        let legacy = LEGACY_ASSETS.load(storage, asset.clone())?;
+
+        ASSETS.save(
+            storage,
+            asset,
+            &Asset {
+                feeders: vec![legacy.feeder],
+                feed_config: FeedConfig::default(),
+                guard: PriceGuard::default(),
+                frozen: None,
+                metadata: None,
+                price: legacy.price.map(|price| PriceInfo {
+                    price,
+                    time: env.block.time,
+                    height: env.block.height,
+                    source: PriceSource::Owner {},
+                }),
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// `CHAINS_CONTRACT` used to store a single remote oracle for every chain.
+/// Rewrite every legacy entry as a list with one oracle. The entries already migrated are skipped.
+fn migrate_chains_contract(storage: &mut dyn Storage) -> StdResult<()> {
+    const LEGACY_CHAINS_CONTRACT: Map<String, String> = Map::new("chains_contracts");
+
+    // The values can't be deserialized before the migration, only the raw keys are read
+    let chains = CHAINS_CONTRACT
+        .keys_raw(storage, None, None, Order::Ascending)
+        .map(|key| String::from_utf8(key).map_err(StdError::from))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    for chain in chains {
+        if CHAINS_CONTRACT.load(storage, chain.clone()).is_ok() {
+            continue;
         }
+
+        let contract = LEGACY_CHAINS_CONTRACT.load(storage, chain.clone())?;
+
+        CHAINS_CONTRACT.save(storage, chain, &vec![contract])?;
     }
+
+    Ok(())
 }
 
 // --- RUN ---
@@ -73,17 +292,29 @@ fn run_regiser_asset(
     deps: DepsMut,
     sender: Addr,
     asset: String,
-    feeder: Addr,
+    feeders: Vec<Addr>,
+    feed_config: Option<FeedConfig>,
+    metadata: Option<AssetMetadata>,
 ) -> Result<Response, ContractError> {
     onlyowner(deps.storage, &sender)?;
 
+    let feed_config = feed_config.unwrap_or_default();
+
+    validate_feed_config(&feed_config, &feeders)?;
+
+    update_pair(deps.storage, &asset, None, metadata.as_ref())?;
+
     match ASSETS.load(deps.storage, asset.clone()) {
         Ok(_) => return Err(ContractError::AssetAlredyRegistered { asset }),
         Err(_) => ASSETS.save(
             deps.storage,
             asset.clone(),
             &Asset {
-                feeder: feeder.clone(),
+                feeders: feeders.clone(),
+                feed_config,
+                guard: PriceGuard::default(),
+                frozen: None,
+                metadata,
                 price: None,
             },
         )?,
@@ -92,211 +323,2002 @@ fn run_regiser_asset(
     Ok(Response::new()
         .add_attribute("action", "register_asset")
         .add_attribute("asset", asset)
-        .add_attribute("feeder", feeder))
+        .add_attributes(
+            feeders
+                .into_iter()
+                .map(|feeder| Attribute::new("feeder", feeder)),
+        ))
 }
 
-fn run_feed_price(
+fn run_update_feeder(
     deps: DepsMut,
     sender: Addr,
     asset: String,
-    price: Decimal,
+    old_feeder: Addr,
+    new_feeder: Addr,
 ) -> Result<Response, ContractError> {
-    onlyfeeder(deps.storage, &sender, &asset)?;
+    onlyowner(deps.storage, &sender)?;
 
-    ASSETS.update(
-        deps.storage,
-        asset.clone(),
-        |asset| -> Result<Asset, StdError> {
-            let mut asset = asset.unwrap();
+    let mut asset_info = ASSETS.load(deps.storage, asset.clone())?;
 
-            asset.price = Some(price);
+    if asset_info.feeders.contains(&new_feeder) {
+        return Err(ContractError::DuplicateFeeder {
+            feeder: new_feeder.to_string(),
+        });
+    }
 
-            Ok(asset)
-        },
-    )?;
+    match asset_info
+        .feeders
+        .iter_mut()
+        .find(|feeder| **feeder == old_feeder)
+    {
+        Some(feeder) => *feeder = new_feeder.clone(),
+        None => {
+            return Err(ContractError::FeederNotFound {
+                feeder: old_feeder.to_string(),
+            })
+        }
+    }
+
+    ASSETS.save(deps.storage, asset.clone(), &asset_info)?;
+    SUBMISSIONS.remove(deps.storage, (asset.clone(), old_feeder.clone()));
 
     Ok(Response::new()
-        .add_attribute("action", "feed_price")
+        .add_attribute("action", "update_feeder")
         .add_attribute("asset", asset)
-        .add_attribute("price", price.to_string()))
+        .add_attribute("old_feeder", old_feeder)
+        .add_attribute("new_feeder", new_feeder))
 }
 
-fn run_feed_remote_price(
+fn run_set_asset_metadata(
     deps: DepsMut,
     sender: Addr,
-    funds: Vec<Coin>,
     asset: String,
-    chain: String,
+    metadata: Option<AssetMetadata>,
 ) -> Result<Response, ContractError> {
-    onlyfeeder(deps.storage, &sender, &asset)?;
+    onlyowner(deps.storage, &sender)?;
 
-    let remote_contract = CHAINS_CONTRACT.load(deps.storage, chain.clone())?;
+    let mut asset_info = ASSETS.load(deps.storage, asset.clone())?;
 
-    let msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: GATE.load(deps.storage)?.to_string(),
+    update_pair(
+        deps.storage,
+        &asset,
+        asset_info.metadata.as_ref(),
+        metadata.as_ref(),
+    )?;
 
-        msg: to_binary(&GateExecuteMsg::SendRequests {
-            requests: vec![GateRequest::Query {
-                queries: vec![QueryRequest::Wasm(WasmQuery::Smart {
-                    contract_addr: remote_contract,
-                    msg: to_binary(&QueryMsg::Price {
-                        asset: asset.clone(),
-                    })?,
-                })],
-                callback_msg: None,
-            }],
-            chain: chain.clone(),
-            timeout: None,
-        })?,
+    asset_info.metadata = metadata;
 
-        funds,
-    });
+    ASSETS.save(deps.storage, asset.clone(), &asset_info)?;
 
     Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "feed_remote_price")
-        .add_attribute("chain", chain)
+        .add_attribute("action", "set_asset_metadata")
         .add_attribute("asset", asset))
 }
 
-fn run_register_gate(
+fn run_remove_asset(deps: DepsMut, sender: Addr, asset: String) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    let asset_info = ASSETS.load(deps.storage, asset.clone())?;
+
+    ASSETS.remove(deps.storage, asset.clone());
+
+    update_pair(deps.storage, &asset, asset_info.metadata.as_ref(), None)?;
+
+    for feeder in asset_info.feeders {
+        SUBMISSIONS.remove(deps.storage, (asset.clone(), feeder));
+    }
+
+    let count = HISTORY_COUNT
+        .may_load(deps.storage, asset.clone())?
+        .unwrap_or_default();
+
+    for seq in count.saturating_sub(HISTORY_SIZE)..count {
+        HISTORY.remove(deps.storage, (asset.clone(), seq % HISTORY_SIZE));
+    }
+
+    HISTORY_COUNT.remove(deps.storage, asset.clone());
+
+    let chains = REMOTE_PRICES
+        .prefix(asset.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+
+    for chain in chains {
+        REMOTE_PRICES.remove(deps.storage, (asset.clone(), chain));
+    }
+
+    let subscriptions = SUBSCRIPTIONS
+        .sub_prefix(asset.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<((String, Addr), Subscription)>>>()?;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+
+    for ((chain, subscriber), subscription) in subscriptions {
+        SUBSCRIPTIONS.remove(deps.storage, (asset.clone(), chain, subscriber));
+        msgs.extend(refund_subscription_msg(subscription));
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "remove_asset")
+        .add_attribute("asset", asset))
+}
+
+fn run_set_feed_config(
     deps: DepsMut,
     sender: Addr,
-    contract: Addr,
+    asset: String,
+    feed_config: FeedConfig,
 ) -> Result<Response, ContractError> {
     onlyowner(deps.storage, &sender)?;
 
-    GATE.save(deps.storage, &contract)?;
+    let mut asset_info = ASSETS.load(deps.storage, asset.clone())?;
+
+    validate_feed_config(&feed_config, &asset_info.feeders)?;
+
+    asset_info.feed_config = feed_config;
+
+    ASSETS.save(deps.storage, asset.clone(), &asset_info)?;
 
     Ok(Response::new()
-        .add_attribute("action", "register_gate")
-        .add_attribute("contract", contract))
+        .add_attribute("action", "set_feed_config")
+        .add_attribute("asset", asset))
 }
 
-fn run_gate_set_permission(
+fn run_feed_price(
     deps: DepsMut,
+    env: Env,
     sender: Addr,
-    contract: String,
-    chain: String,
+    asset: String,
+    price: Decimal,
+) -> Result<Response, ContractError> {
+    onlyfeeder(deps.storage, &sender, &asset)?;
+
+    let asset_info = ASSETS.load(deps.storage, asset.clone())?;
+
+    if asset_info.frozen.is_some() {
+        return Err(ContractError::AssetFrozen { asset });
+    }
+
+    SUBMISSIONS.save(
+        deps.storage,
+        (asset.clone(), sender.clone()),
+        &Submission {
+            price,
+            time: env.block.time,
+            flagged: false,
+        },
+    )?;
+
+    let mut response = Response::new()
+        .add_attribute("action", "feed_price")
+        .add_attribute("asset", asset.clone())
+        .add_attribute("price", price.to_string());
+
+    // Fresh submissions of the feeders
+    let mut submissions: Vec<(Addr, Submission)> = vec![];
+
+    for feeder in asset_info.feeders {
+        if let Some(submission) =
+            SUBMISSIONS.may_load(deps.storage, (asset.clone(), feeder.clone()))?
+        {
+            if submission.time.plus_seconds(asset_info.feed_config.window) >= env.block.time {
+                submissions.push((feeder, submission));
+            }
+        }
+    }
+
+    if (submissions.len() as u32) < asset_info.feed_config.quorum {
+        return Ok(response.add_attribute("published", "false"));
+    }
+
+    let published = aggregate_prices(
+        submissions
+            .iter()
+            .map(|(_, submission)| submission.price)
+            .collect(),
+        &asset_info.feed_config.aggregation,
+    )?;
+
+    if !check_guard(&asset_info.guard, asset_info.price.as_ref(), published) {
+        match asset_info.guard.on_violation {
+            GuardAction::Reject {} => {
+                return Err(ContractError::PriceOutOfBounds {
+                    asset,
+                    price: published.to_string(),
+                })
+            }
+            GuardAction::Freeze {} => {
+                ASSETS.update(deps.storage, asset.clone(), |asset| -> StdResult<Asset> {
+                    let mut asset = asset.ok_or(StdError::not_found("Asset"))?;
+
+                    asset.frozen = Some(Frozen {
+                        price: published,
+                        time: env.block.time,
+                    });
+
+                    Ok(asset)
+                })?;
+
+                return Ok(response
+                    .add_attribute("published", "false")
+                    .add_attribute("frozen_price", published.to_string()));
+            }
+        }
+    }
+
+    for (feeder, mut submission) in submissions.clone() {
+        submission.flagged = match asset_info.feed_config.max_deviation {
+            // A deviation too large to be represented is flagged
+            Some(max_deviation) if !published.is_zero() => submission
+                .price
+                .abs_diff(published)
+                .checked_div(published)
+                .map_or(true, |deviation| deviation > max_deviation),
+            _ => false,
+        };
+
+        if submission.flagged {
+            response = response.add_attribute("flagged_feeder", feeder.clone());
+        }
+
+        SUBMISSIONS.save(deps.storage, (asset.clone(), feeder), &submission)?;
+    }
+
+    save_price(
+        deps.storage,
+        &env,
+        &asset,
+        published,
+        PriceSource::Local {
+            feeders: submissions.into_iter().map(|(feeder, _)| feeder).collect(),
+        },
+    )?;
+
+    Ok(response
+        .add_attribute("published", "true")
+        .add_attribute("published_price", published.to_string()))
+}
+
+fn run_set_price_guard(
+    deps: DepsMut,
+    sender: Addr,
+    asset: String,
+    guard: PriceGuard,
 ) -> Result<Response, ContractError> {
     onlyowner(deps.storage, &sender)?;
 
-    CHAINS_CONTRACT.save(deps.storage, chain.clone(), &contract)?;
+    if let (Some(min_price), Some(max_price)) = (guard.min_price, guard.max_price) {
+        if min_price > max_price {
+            return Err(ContractError::InvalidPriceGuard {});
+        }
+    }
 
-    let msg = CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute {
-        contract_addr: GATE.load(deps.storage)?.to_string(),
-        msg: to_binary(&GateExecuteMsg::SetPermission {
-            permission: Permission::Permissioned {
-                addresses: vec![contract.clone()],
-            },
-            chain,
-        })?,
-        funds: vec![],
-    });
+    let mut asset_info = ASSETS.load(deps.storage, asset.clone())?;
+
+    asset_info.guard = guard;
+
+    ASSETS.save(deps.storage, asset.clone(), &asset_info)?;
 
     Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "register_remote_contract")
-        .add_attribute("value", contract))
+        .add_attribute("action", "set_price_guard")
+        .add_attribute("asset", asset))
 }
 
-// --- GATE ---
-
-fn gate_receive_msg(
+fn run_resolve_frozen(
     deps: DepsMut,
-    info: MessageInfo,
-    msg: GateMsg,
+    env: Env,
+    sender: Addr,
+    asset: String,
+    price: Option<Decimal>,
 ) -> Result<Response, ContractError> {
-    match msg {
-        GateMsg::QueryResponse {
-            queries,
-            callback_msg,
-        } => run_gate_query_response(deps, info.sender, queries, callback_msg),
+    onlyowner(deps.storage, &sender)?;
 
-        _ => Err(ContractError::Std(StdError::generic_err(format!(
-            "{:?} not implemented on mock_deposit",
-            msg
-        )))),
+    let mut asset_info = ASSETS.load(deps.storage, asset.clone())?;
+
+    if asset_info.frozen.take().is_none() {
+        return Err(ContractError::AssetNotFrozen { asset });
     }
+
+    ASSETS.save(deps.storage, asset.clone(), &asset_info)?;
+
+    let mut response = Response::new()
+        .add_attribute("action", "resolve_frozen")
+        .add_attribute("asset", asset.clone());
+
+    if let Some(price) = price {
+        save_price(deps.storage, &env, &asset, price, PriceSource::Owner {})?;
+
+        response = response.add_attribute("price", price.to_string());
+    }
+
+    Ok(response)
 }
 
-fn run_gate_query_response(
+fn run_feed_remote_price(
     deps: DepsMut,
+    env: Env,
     sender: Addr,
-    queries: Vec<GateQueryResponse>,
-    _callback_msg: Option<Binary>,
+    funds: Vec<Coin>,
+    asset: String,
+    chain: String,
 ) -> Result<Response, ContractError> {
-    onlygate(deps.storage, &sender)?;
+    onlyfeeder(deps.storage, &sender, &asset)?;
 
-    let mut attributes: Vec<Attribute> = vec![Attribute::new("action", "gate_query_response")];
+    let msg = request_remote_prices(
+        deps.storage,
+        &env,
+        vec![asset.clone()],
+        chain.clone(),
+        funds,
+        None,
+    )?;
 
-    for query in queries {
-        match query.request {
-            QueryRequest::Wasm(WasmQuery::Smart { msg, .. }) => match from_binary(&msg)? {
-                QueryMsg::Price { asset } => {
-                    let price: Decimal = from_binary(&query.response)?;
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("action", "feed_remote_price")
+        .add_attribute("chain", chain)
+        .add_attribute("asset", asset))
+}
 
-                    ASSETS.update(
-                        deps.storage,
-                        asset.clone(),
-                        |asset| -> Result<Asset, StdError> {
-                            let mut asset = asset.unwrap();
+fn run_feed_remote_prices(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    funds: Vec<Coin>,
+    assets: Vec<String>,
+    chains: Vec<String>,
+) -> Result<Response, ContractError> {
+    if assets.is_empty() {
+        return Err(ContractError::EmptyList {
+            name: "assets".to_string(),
+        });
+    }
 
-                            asset.price = Some(price);
+    if chains.is_empty() {
+        return Err(ContractError::EmptyList {
+            name: "chains".to_string(),
+        });
+    }
 
-                            Ok(asset)
-                        },
-                    )?;
+    for asset in &assets {
+        onlyfeeder(deps.storage, &sender, asset)?;
+    }
 
-                    attributes.push(Attribute::new("asset", asset));
-                    attributes.push(Attribute::new("price", price.to_string()))
-                }
-                _ => return Err(ContractError::Std(StdError::generic_err("Unimplemented"))),
-            },
+    let mut msgs: Vec<CosmosMsg> = vec![];
 
-            _ => return Err(ContractError::Std(StdError::generic_err("Unimplemented"))),
-        }
+    for (chain, funds) in chains.iter().zip(split_funds(funds, chains.len())) {
+        msgs.push(request_remote_prices(
+            deps.storage,
+            &env,
+            assets.clone(),
+            chain.clone(),
+            funds,
+            None,
+        )?);
     }
 
-    Ok(Response::new().add_attributes(attributes))
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "feed_remote_prices")
+        .add_attributes(
+            chains
+                .into_iter()
+                .map(|chain| Attribute::new("chain", chain)),
+        )
+        .add_attributes(
+            assets
+                .into_iter()
+                .map(|asset| Attribute::new("asset", asset)),
+        ))
 }
 
-// --- QUERIES ---
+fn run_push_prices(
+    deps: DepsMut,
+    funds: Vec<Coin>,
+    assets: Vec<String>,
+    chains: Vec<String>,
+) -> Result<Response, ContractError> {
+    if assets.is_empty() {
+        return Err(ContractError::EmptyList {
+            name: "assets".to_string(),
+        });
+    }
 
-fn qy_price(deps: Deps, asset: String) -> StdResult<Decimal> {
-    let price = ASSETS.load(deps.storage, asset)?.price;
+    if chains.is_empty() {
+        return Err(ContractError::EmptyList {
+            name: "chains".to_string(),
+        });
+    }
 
-    match price {
-        Some(price) => Ok(price),
-        None => Err(StdError::generic_err("Price never feeded")),
+    let prices = qy_prices_by_list(deps.as_ref(), assets)?;
+
+    if prices.is_empty() {
+        return Err(ContractError::PriceNeverFeeded {});
     }
-}
 
-fn qy_prices(
-    deps: Deps,
-    start_after: Option<String>,
-    limit: Option<u64>,
-) -> StdResult<Vec<(String, Option<Decimal>)>> {
-    let limit = match limit {
-        Some(value) => min(value, MAX_LIMIT),
-        None => DEFAULT_LIMIT,
-    };
+    let msg = to_binary(&OracleGateMsg::PriceUpdate {
+        prices: prices.clone(),
+    })?;
 
-    let start: Option<Bound<String>> = start_after.map(Bound::exclusive);
+    let mut msgs: Vec<CosmosMsg> = vec![];
 
-    let prices: Vec<(String, Option<Decimal>)> = ASSETS
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit.try_into().unwrap())
-        .map(|item| {
-            let (asset, info) = item.unwrap();
-            (asset, info.price)
-        })
-        .collect();
+    for (chain, funds) in chains.iter().zip(split_funds(funds, chains.len())) {
+        let legacy_contracts = CHAINS_LEGACY_CONTRACT
+            .may_load(deps.storage, chain.clone())?
+            .unwrap_or_default();
 
-    Ok(prices)
-}
+        // A legacy oracle would reject the msg, failing the whole packet
+        let requests: Vec<GateRequest> = load_chain_contracts(deps.storage, chain)?
+            .into_iter()
+            .filter(|contract| !legacy_contracts.contains(contract))
+            .map(|to_contract| GateRequest::SendMsg {
+                msg: msg.clone(),
+                to_contract,
+                send_native: None,
+            })
+            .collect();
 
-// --- FUNCTIONS ---
+        if requests.is_empty() {
+            return Err(ContractError::PushNotSupported {
+                chain: chain.clone(),
+            });
+        }
+
+        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: GATE.load(deps.storage)?.to_string(),
+            msg: to_binary(&GateExecuteMsg::SendRequests {
+                requests,
+                chain: chain.to_string(),
+                timeout: None,
+            })?,
+            funds,
+        }));
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "push_prices")
+        .add_attributes(
+            chains
+                .into_iter()
+                .map(|chain| Attribute::new("chain", chain)),
+        )
+        .add_attributes(
+            prices
+                .into_iter()
+                .map(|(asset, _)| Attribute::new("asset", asset)),
+        ))
+}
+
+fn run_request_remote_price(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    funds: Vec<Coin>,
+    asset: String,
+    chain: String,
+    callback: Binary,
+) -> Result<Response, ContractError> {
+    ASSETS.load(deps.storage, asset.clone())?;
+
+    let msg = request_remote_prices(
+        deps.storage,
+        &env,
+        vec![asset.clone()],
+        chain.clone(),
+        funds,
+        Some(PriceCallback {
+            receiver: sender.clone(),
+            msg: callback,
+        }),
+    )?;
+
+    Ok(Response::new()
+        .add_message(msg)
+        .add_attribute("action", "request_remote_price")
+        .add_attribute("receiver", sender)
+        .add_attribute("chain", chain)
+        .add_attribute("asset", asset))
+}
+
+fn run_retry_request(
+    deps: DepsMut,
+    env: Env,
+    funds: Vec<Coin>,
+    request_id: u64,
+) -> Result<Response, ContractError> {
+    let mut request = PENDING_REQUESTS.load(deps.storage, request_id)?;
+
+    if request.status != (RequestStatus::Failed {}) {
+        return Err(ContractError::RequestNotFailed { request_id });
+    }
+
+    request.status = RequestStatus::Pending {};
+    request.sent = env.block.time;
+    request.attempts += 1;
+
+    PENDING_REQUESTS.save(deps.storage, request_id, &request)?;
+
+    Ok(Response::new()
+        .add_message(remote_prices_msg(
+            deps.storage,
+            request_id,
+            &request,
+            funds,
+        )?)
+        .add_attribute("action", "retry_request")
+        .add_attribute("request_id", request_id.to_string())
+        .add_attribute("attempts", request.attempts.to_string()))
+}
+
+fn run_set_refresh_fees(
+    deps: DepsMut,
+    sender: Addr,
+    fees: Option<RefreshFees>,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    match &fees {
+        Some(fees) => REFRESH_FEES.save(deps.storage, fees)?,
+        None => REFRESH_FEES.remove(deps.storage),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_refresh_fees")
+        .add_attribute(
+            "total_fee",
+            fees.map(|fees| format!("{}{}", fees.total(), fees.denom))
+                .unwrap_or_default(),
+        ))
+}
+
+fn run_subscribe(
+    deps: DepsMut,
+    sender: Addr,
+    funds: Vec<Coin>,
+    asset: String,
+    chain: String,
+    min_interval: u64,
+) -> Result<Response, ContractError> {
+    ASSETS.load(deps.storage, asset.clone())?;
+    load_chain_contracts(deps.storage, &chain)?;
+
+    if min_interval > MAX_MIN_INTERVAL {
+        return Err(ContractError::InvalidMinInterval {
+            max: MAX_MIN_INTERVAL,
+        });
+    }
+
+    let key = (asset.clone(), chain.clone(), sender.clone());
+
+    let mut subscription = match SUBSCRIPTIONS.may_load(deps.storage, key.clone())? {
+        Some(subscription) => Subscription {
+            min_interval,
+            ..subscription
+        },
+        None => Subscription {
+            subscriber: sender.clone(),
+            min_interval,
+            last_refresh: None,
+            balance: None,
+        },
+    };
+
+    add_subscription_funds(deps.storage, &mut subscription, funds)?;
+
+    SUBSCRIPTIONS.save(deps.storage, key, &subscription)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "subscribe")
+        .add_attribute("subscriber", sender)
+        .add_attribute("asset", asset)
+        .add_attribute("chain", chain)
+        .add_attribute("min_interval", min_interval.to_string()))
+}
+
+fn run_fund_subscription(
+    deps: DepsMut,
+    funds: Vec<Coin>,
+    asset: String,
+    chain: String,
+    subscriber: String,
+) -> Result<Response, ContractError> {
+    let subscriber = deps.api.addr_validate(&subscriber)?;
+
+    let key = (asset.clone(), chain.clone(), subscriber.clone());
+
+    let mut subscription = SUBSCRIPTIONS.load(deps.storage, key.clone())?;
+
+    add_subscription_funds(deps.storage, &mut subscription, funds)?;
+
+    SUBSCRIPTIONS.save(deps.storage, key, &subscription)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "fund_subscription")
+        .add_attribute("subscriber", subscriber)
+        .add_attribute("asset", asset)
+        .add_attribute("chain", chain))
+}
+
+fn run_unsubscribe(
+    deps: DepsMut,
+    sender: Addr,
+    asset: String,
+    chain: String,
+    subscriber: Option<String>,
+) -> Result<Response, ContractError> {
+    let subscriber = match subscriber {
+        Some(subscriber) => deps.api.addr_validate(&subscriber)?,
+        None => sender.clone(),
+    };
+
+    if subscriber != sender {
+        onlyowner(deps.storage, &sender)?;
+    }
+
+    let key = (asset.clone(), chain.clone(), subscriber.clone());
+
+    let subscription = SUBSCRIPTIONS.load(deps.storage, key.clone())?;
+
+    SUBSCRIPTIONS.remove(deps.storage, key);
+
+    Ok(Response::new()
+        .add_messages(refund_subscription_msg(subscription))
+        .add_attribute("action", "unsubscribe")
+        .add_attribute("subscriber", subscriber)
+        .add_attribute("asset", asset)
+        .add_attribute("chain", chain))
+}
+
+fn run_refresh_due(deps: DepsMut, env: Env, keeper: Addr) -> Result<Response, ContractError> {
+    let fees = REFRESH_FEES.may_load(deps.storage)?;
+
+    let subscriptions = SUBSCRIPTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(SubscriptionKey, Subscription)>>>()?;
+
+    // Assets to refresh for every chain
+    let mut due: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for ((asset, chain, subscriber), mut subscription) in subscriptions {
+        if let Some(last_refresh) = subscription.last_refresh {
+            // A next refresh out of the time range is never due
+            match last_refresh
+                .seconds()
+                .checked_add(subscription.min_interval)
+            {
+                Some(next_refresh) if next_refresh <= env.block.time.seconds() => {}
+                _ => continue,
+            }
+        }
+
+        // The pair is alredy refreshed for a previous subscriber, it's paid only once
+        let refreshed = due
+            .get(&chain)
+            .is_some_and(|assets| assets.contains(&asset));
+
+        // Subscriptions without enough balance are skipped
+        if let (Some(fees), false) = (&fees, refreshed) {
+            match subscription.balance.as_mut() {
+                Some(balance) if balance.denom == fees.denom && balance.amount >= fees.total() => {
+                    balance.amount -= fees.total();
+                }
+                _ => continue,
+            }
+        }
+
+        subscription.last_refresh = Some(env.block.time);
+
+        SUBSCRIPTIONS.save(
+            deps.storage,
+            (asset.clone(), chain.clone(), subscriber),
+            &subscription,
+        )?;
+
+        if !refreshed {
+            due.entry(chain).or_default().push(asset);
+        }
+    }
+
+    if due.is_empty() {
+        return Err(ContractError::NothingDue {});
+    }
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut refreshed: u64 = 0;
+
+    for (chain, assets) in due {
+        let funds = fees
+            .as_ref()
+            .map(|fees| Coin {
+                denom: fees.denom.clone(),
+                amount: fees.gate_fee * Uint128::from(assets.len() as u128),
+            })
+            .filter(|fee| !fee.amount.is_zero())
+            .into_iter()
+            .collect();
+
+        refreshed += assets.len() as u64;
+
+        msgs.push(request_remote_prices(
+            deps.storage,
+            &env,
+            assets,
+            chain,
+            funds,
+            None,
+        )?);
+    }
+
+    if let Some(reward) = fees
+        .map(|fees| Coin {
+            denom: fees.denom,
+            amount: fees.keeper_reward * Uint128::from(refreshed),
+        })
+        .filter(|reward| !reward.amount.is_zero())
+    {
+        msgs.push(
+            BankMsg::Send {
+                to_address: keeper.to_string(),
+                amount: vec![reward],
+            }
+            .into(),
+        );
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "refresh_due")
+        .add_attribute("keeper", keeper)
+        .add_attribute("refreshed", refreshed.to_string()))
+}
+
+fn run_register_gate(
+    deps: DepsMut,
+    sender: Addr,
+    contract: Addr,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    GATE.save(deps.storage, &contract)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_gate")
+        .add_attribute("contract", contract))
+}
+
+fn run_gate_set_permission(
+    deps: DepsMut,
+    sender: Addr,
+    contract: String,
+    chain: String,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    let mut contracts = CHAINS_CONTRACT
+        .may_load(deps.storage, chain.clone())?
+        .unwrap_or_default();
+
+    if contracts.contains(&contract) {
+        return Err(ContractError::RemoteContractAlredyRegistered { contract, chain });
+    }
+
+    contracts.push(contract.clone());
+
+    CHAINS_CONTRACT.save(deps.storage, chain.clone(), &contracts)?;
+
+    Ok(Response::new()
+        .add_message(gate_set_permission_msg(deps.storage, chain)?)
+        .add_attribute("action", "register_remote_contract")
+        .add_attribute("value", contract))
+}
+
+fn run_gate_remove_permission(
+    deps: DepsMut,
+    sender: Addr,
+    contract: String,
+    chain: String,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    let mut contracts = load_chain_contracts(deps.storage, &chain)?;
+
+    if !contracts.contains(&contract) {
+        return Err(ContractError::RemoteContractNotRegistered { contract, chain });
+    }
+
+    contracts.retain(|value| *value != contract);
+
+    if contracts.is_empty() {
+        return run_remove_chain(deps, sender, chain);
+    }
+
+    CHAINS_CONTRACT.save(deps.storage, chain.clone(), &contracts)?;
+
+    let mut legacy_contracts = CHAINS_LEGACY_CONTRACT
+        .may_load(deps.storage, chain.clone())?
+        .unwrap_or_default();
+
+    legacy_contracts.retain(|value| *value != contract);
+
+    CHAINS_LEGACY_CONTRACT.save(deps.storage, chain.clone(), &legacy_contracts)?;
+
+    Ok(Response::new()
+        .add_message(gate_set_permission_msg(deps.storage, chain)?)
+        .add_attribute("action", "remove_remote_contract")
+        .add_attribute("value", contract))
+}
+
+fn run_gate_set_permission_mode(
+    deps: DepsMut,
+    sender: Addr,
+    chain: String,
+    mode: PermissionMode,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    load_chain_contracts(deps.storage, &chain)?;
+
+    CHAINS_PERMISSION.save(deps.storage, chain.clone(), &mode)?;
+
+    Ok(Response::new()
+        .add_message(gate_set_permission_msg(deps.storage, chain.clone())?)
+        .add_attribute("action", "set_permission_mode")
+        .add_attribute("chain", chain))
+}
+
+fn run_set_legacy_contract(
+    deps: DepsMut,
+    sender: Addr,
+    contract: String,
+    chain: String,
+    legacy: bool,
+) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    if !load_chain_contracts(deps.storage, &chain)?.contains(&contract) {
+        return Err(ContractError::RemoteContractNotRegistered { contract, chain });
+    }
+
+    let mut legacy_contracts = CHAINS_LEGACY_CONTRACT
+        .may_load(deps.storage, chain.clone())?
+        .unwrap_or_default();
+
+    legacy_contracts.retain(|value| *value != contract);
+
+    if legacy {
+        legacy_contracts.push(contract.clone());
+    }
+
+    CHAINS_LEGACY_CONTRACT.save(deps.storage, chain.clone(), &legacy_contracts)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_legacy_contract")
+        .add_attribute("contract", contract)
+        .add_attribute("chain", chain)
+        .add_attribute("legacy", legacy.to_string()))
+}
+
+fn run_remove_chain(deps: DepsMut, sender: Addr, chain: String) -> Result<Response, ContractError> {
+    onlyowner(deps.storage, &sender)?;
+
+    load_chain_contracts(deps.storage, &chain)?;
+
+    CHAINS_CONTRACT.remove(deps.storage, chain.clone());
+    CHAINS_PERMISSION.remove(deps.storage, chain.clone());
+    CHAINS_LEGACY_CONTRACT.remove(deps.storage, chain.clone());
+
+    let mut msgs: Vec<CosmosMsg> = vec![gate_set_permission_msg(deps.storage, chain.clone())?];
+
+    let remote_prices = REMOTE_PRICES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .filter(|key| {
+            key.as_ref()
+                .map_or(true, |(_, key_chain)| *key_chain == chain)
+        })
+        .collect::<StdResult<Vec<(String, String)>>>()?;
+
+    for key in remote_prices {
+        REMOTE_PRICES.remove(deps.storage, key);
+    }
+
+    let subscriptions = SUBSCRIPTIONS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |((_, key_chain, _), _)| *key_chain == chain)
+        })
+        .collect::<StdResult<Vec<(SubscriptionKey, Subscription)>>>()?;
+
+    for (key, subscription) in subscriptions {
+        SUBSCRIPTIONS.remove(deps.storage, key);
+        msgs.extend(refund_subscription_msg(subscription));
+    }
+
+    let requests = PENDING_REQUESTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(_, request)| request.chain == chain)
+        })
+        .map(|item| item.map(|(request_id, _)| request_id))
+        .collect::<StdResult<Vec<u64>>>()?;
+
+    for request_id in requests {
+        PENDING_REQUESTS.remove(deps.storage, request_id);
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_attribute("action", "remove_chain")
+        .add_attribute("chain", chain))
+}
+
+// --- GATE ---
+
+fn gate_receive_msg(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: GateMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        GateMsg::QueryResponse {
+            queries,
+            callback_msg,
+        } => run_gate_query_response(deps, env, info.sender, queries, callback_msg),
+        GateMsg::ReceivedMsg { sender, msg } => {
+            run_gate_received_msg(deps, env, info.sender, sender, msg)
+        }
+        GateMsg::RequestFailed { request } => run_gate_request_failed(deps, info.sender, request),
+
+        _ => Err(ContractError::Std(StdError::generic_err(format!(
+            "{:?} not implemented on mock_deposit",
+            msg
+        )))),
+    }
+}
+
+/// Save the prices pushed by a remote oracle, verified against the ones registered in `CHAINS_CONTRACT`.
+/// Assets not registered on this oracle are skipped.
+fn run_gate_received_msg(
+    deps: DepsMut,
+    env: Env,
+    gate: Addr,
+    remote_contract: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    onlygate(deps.storage, &gate)?;
+
+    let chain = find_chain(deps.storage, &remote_contract)?;
+
+    let mut attributes: Vec<Attribute> = vec![
+        Attribute::new("action", "gate_received_msg"),
+        Attribute::new("chain", chain.clone()),
+    ];
+
+    match from_binary(&msg)? {
+        OracleGateMsg::PriceUpdate { prices } => {
+            for (asset, info) in prices {
+                if !ASSETS.has(deps.storage, asset.clone()) {
+                    continue;
+                }
+
+                if save_remote_price(
+                    deps.storage,
+                    &env,
+                    &asset,
+                    &chain,
+                    info.price,
+                    Some(info.time),
+                )?
+                .is_none()
+                {
+                    continue;
+                }
+
+                attributes.push(Attribute::new("asset", asset));
+                attributes.push(Attribute::new("price", info.price.to_string()));
+            }
+        }
+    }
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+/// Mark the remote price request as failed, so that it can be retried.
+/// The failed pushes of `PushPrices` are not tracked.
+fn run_gate_request_failed(
+    deps: DepsMut,
+    gate: Addr,
+    request: GateRequest,
+) -> Result<Response, ContractError> {
+    onlygate(deps.storage, &gate)?;
+
+    let mut response = Response::new().add_attribute("action", "gate_request_failed");
+
+    if let GateRequest::Query {
+        callback_msg: Some(callback_msg),
+        ..
+    } = request
+    {
+        let request_id = from_binary::<RequestCallback>(&callback_msg)?.request_id;
+
+        if let Some(mut request) = PENDING_REQUESTS.may_load(deps.storage, request_id)? {
+            request.status = RequestStatus::Failed {};
+
+            PENDING_REQUESTS.save(deps.storage, request_id, &request)?;
+
+            response = response
+                .add_attribute("request_id", request_id.to_string())
+                .add_attribute("chain", request.chain);
+        }
+    }
+
+    Ok(response)
+}
+
+fn run_gate_query_response(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    queries: Vec<GateQueryResponse>,
+    callback_msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+    onlygate(deps.storage, &sender)?;
+
+    let request: Option<PendingRequest> = match callback_msg {
+        Some(msg) => {
+            let request_id = from_binary::<RequestCallback>(&msg)?.request_id;
+            let request = PENDING_REQUESTS.may_load(deps.storage, request_id)?;
+
+            PENDING_REQUESTS.remove(deps.storage, request_id);
+
+            request
+        }
+        None => None,
+    };
+
+    let callback: Option<PriceCallback> = request
+        .as_ref()
+        .and_then(|request| request.callback.clone());
+
+    let mut attributes: Vec<Attribute> = vec![Attribute::new("action", "gate_query_response")];
+    let mut msgs: Vec<SubMsg> = vec![];
+
+    for query in queries {
+        let (contract_addr, msg) = match query.request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => (contract_addr, msg),
+            _ => return Err(ContractError::Std(StdError::generic_err("Unimplemented"))),
+        };
+
+        // List of (asset, price, time of the update on the remote chain)
+        let prices: Vec<(String, Decimal, Option<Timestamp>)> = match from_binary(&msg)? {
+            QueryMsg::Price { asset, .. } => vec![(asset, from_binary(&query.response)?, None)],
+            QueryMsg::PriceWithMeta { asset } => {
+                let info: PriceInfo = from_binary(&query.response)?;
+                vec![(asset, info.price, Some(info.time))]
+            }
+            QueryMsg::PricesByList { .. } => {
+                from_binary::<Vec<(String, PriceInfo)>>(&query.response)?
+                    .into_iter()
+                    .map(|(asset, info)| (asset, info.price, Some(info.time)))
+                    .collect()
+            }
+            _ => return Err(ContractError::Std(StdError::generic_err("Unimplemented"))),
+        };
+
+        // The oracle queried could have been removed from the chain in the meantime
+        let chain = match &request {
+            Some(request) => request.chain.clone(),
+            None => find_chain(deps.storage, &contract_addr)?,
+        };
+
+        for (asset, price, remote_time) in prices {
+            // The assets removed in the meantime are skipped
+            if !ASSETS.has(deps.storage, asset.clone()) {
+                continue;
+            }
+
+            // An older price than the one stored is skipped
+            let info =
+                match save_remote_price(deps.storage, &env, &asset, &chain, price, remote_time)? {
+                    Some(info) => info,
+                    None => continue,
+                };
+
+            attributes.push(Attribute::new("asset", asset.clone()));
+            attributes.push(Attribute::new("chain", chain.clone()));
+            attributes.push(Attribute::new("price", price.to_string()));
+
+            // A failing consumer doesn't revert the prices saved and the request removed
+            if let Some(callback) = &callback {
+                msgs.push(SubMsg::reply_on_error(
+                    WasmMsg::Execute {
+                        contract_addr: callback.receiver.to_string(),
+                        msg: to_binary(&PriceReceiverMsg::RemotePriceCallback {
+                            asset,
+                            chain: chain.clone(),
+                            price: info,
+                            msg: callback.msg.clone(),
+                        })?,
+                        funds: vec![],
+                    },
+                    MsgReplyID::PriceCallback.repr(),
+                ));
+            }
+        }
+    }
+
+    Ok(Response::new()
+        .add_submessages(msgs)
+        .add_attributes(attributes))
+}
+
+// --- QUERIES ---
+
+fn qy_price(
+    deps: Deps,
+    env: Env,
+    asset: String,
+    max_age: Option<u64>,
+    selector: Option<PriceSelector>,
+) -> Result<PriceResponse, ContractError> {
+    let asset_info = match ASSETS.may_load(deps.storage, asset.clone())? {
+        Some(asset_info) => asset_info,
+        None => {
+            return match (asset.split_once('/'), selector) {
+                (Some((base, quote)), None | Some(PriceSelector::Local {})) => {
+                    Ok(PriceResponse::CrossRate(qy_cross_rate(
+                        deps,
+                        env,
+                        base.to_string(),
+                        quote.to_string(),
+                        max_age,
+                        None,
+                    )?))
+                }
+                _ => Err(StdError::not_found("Asset").into()),
+            }
+        }
+    };
+
+    let price = match selector.unwrap_or(PriceSelector::Local {}) {
+        PriceSelector::Local {} => asset_info.local_price(&asset)?.cloned(),
+        PriceSelector::Remote { chain } => {
+            REMOTE_PRICES.may_load(deps.storage, (asset.clone(), chain))?
+        }
+        PriceSelector::Aggregated {} => {
+            let mut prices: Vec<Decimal> = vec![];
+
+            // The local price is skipped if the asset is frozen
+            let local = asset_info.local_price(&asset).ok().flatten().cloned();
+
+            for info in local.into_iter().chain(
+                REMOTE_PRICES
+                    .prefix(asset.clone())
+                    .range(deps.storage, None, None, Order::Ascending)
+                    .map(|item| item.map(|(_, info)| info))
+                    .collect::<StdResult<Vec<PriceInfo>>>()?,
+            ) {
+                if check_age(&env, &asset, &info, max_age).is_ok() {
+                    prices.push(info.price);
+                }
+            }
+
+            if prices.is_empty() {
+                return Err(ContractError::PriceNeverFeeded {});
+            }
+
+            return Ok(PriceResponse::Price(aggregate_prices(
+                prices,
+                &Aggregation::Median {},
+            )?));
+        }
+    };
+
+    match price {
+        Some(price) => {
+            check_age(&env, &asset, &price, max_age)?;
+
+            Ok(PriceResponse::Price(price.price))
+        }
+        None => Err(ContractError::PriceNeverFeeded {}),
+    }
+}
+
+fn qy_cross_rate(
+    deps: Deps,
+    env: Env,
+    base: String,
+    quote: String,
+    max_age: Option<u64>,
+    max_path_length: Option<u32>,
+) -> Result<CrossRateResponse, ContractError> {
+    let max_path_length = min(max_path_length.unwrap_or(MAX_PATH_LENGTH), MAX_PATH_LENGTH);
+
+    // Usable pairs in both the directions: symbol -> (symbol, asset, inverted, price)
+    let mut edges: BTreeMap<String, Vec<(String, String, bool, PriceInfo)>> = BTreeMap::new();
+
+    for item in PAIRS.range(deps.storage, None, None, Order::Ascending) {
+        let ((pair_base, pair_quote), asset) = item?;
+
+        // Pairs without a fresh price, or frozen, are skipped
+        let price = match ASSETS
+            .load(deps.storage, asset.clone())?
+            .local_price(&asset)
+        {
+            Ok(Some(price)) if check_age(&env, &asset, price, max_age).is_ok() => price.clone(),
+            _ => continue,
+        };
+
+        edges.entry(pair_base.clone()).or_default().push((
+            pair_quote.clone(),
+            asset.clone(),
+            false,
+            price.clone(),
+        ));
+        edges
+            .entry(pair_quote)
+            .or_default()
+            .push((pair_base, asset, true, price));
+    }
+
+    // Breadth first search of the shortest path from base to quote
+    let mut paths: Vec<(String, Vec<PathStep>)> = vec![(base.clone(), vec![])];
+    let mut visited: Vec<String> = vec![base.clone()];
+
+    for _ in 0..max_path_length {
+        let mut next_paths: Vec<(String, Vec<PathStep>)> = vec![];
+
+        for (symbol, path) in paths {
+            for (next, asset, inverted, price) in edges.get(&symbol).into_iter().flatten() {
+                if visited.contains(next) {
+                    continue;
+                }
+
+                let mut path = path.clone();
+
+                path.push(PathStep {
+                    asset: asset.clone(),
+                    inverted: *inverted,
+                    price: price.clone(),
+                });
+
+                if *next == quote {
+                    let price = path.iter().try_fold(Decimal::one(), |rate, step| {
+                        let price = if step.inverted {
+                            Decimal::one().checked_div(step.price.price).map_err(|_| {
+                                ContractError::InvalidPrice {
+                                    asset: step.asset.clone(),
+                                }
+                            })?
+                        } else {
+                            step.price.price
+                        };
+
+                        rate.checked_mul(price)
+                            .map_err(|err| ContractError::Std(err.into()))
+                    })?;
+
+                    return Ok(CrossRateResponse { price, path });
+                }
+
+                visited.push(next.clone());
+                next_paths.push((next.clone(), path));
+            }
+        }
+
+        paths = next_paths;
+    }
+
+    Err(ContractError::NoPath { base, quote })
+}
+
+fn qy_price_with_meta(deps: Deps, asset: String) -> Result<PriceInfo, ContractError> {
+    ASSETS
+        .load(deps.storage, asset.clone())?
+        .local_price(&asset)?
+        .cloned()
+        .ok_or(ContractError::PriceNeverFeeded {})
+}
+
+fn qy_chain(deps: Deps, chain: String) -> Result<ChainResponse, ContractError> {
+    let contracts = load_chain_contracts(deps.storage, &chain)?;
+
+    Ok(ChainResponse {
+        mode: CHAINS_PERMISSION
+            .may_load(deps.storage, chain.clone())?
+            .unwrap_or_default(),
+        legacy_contracts: CHAINS_LEGACY_CONTRACT
+            .may_load(deps.storage, chain.clone())?
+            .unwrap_or_default(),
+        chain,
+        contracts,
+    })
+}
+
+fn qy_chains(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u64>,
+) -> Result<Vec<ChainResponse>, ContractError> {
+    let limit = match limit {
+        Some(value) => min(value, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+
+    CHAINS_CONTRACT
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .map(|item| {
+            let (chain, contracts) = item?;
+
+            Ok(ChainResponse {
+                mode: CHAINS_PERMISSION
+                    .may_load(deps.storage, chain.clone())?
+                    .unwrap_or_default(),
+                legacy_contracts: CHAINS_LEGACY_CONTRACT
+                    .may_load(deps.storage, chain.clone())?
+                    .unwrap_or_default(),
+                chain,
+                contracts,
+            })
+        })
+        .collect()
+}
+
+fn qy_guard(deps: Deps, asset: String) -> Result<GuardResponse, ContractError> {
+    let asset = ASSETS.load(deps.storage, asset)?;
+
+    Ok(GuardResponse {
+        guard: asset.guard,
+        frozen: asset.frozen,
+    })
+}
+
+fn qy_prices_by_list(
+    deps: Deps,
+    assets: Vec<String>,
+) -> Result<Vec<(String, PriceInfo)>, ContractError> {
+    let mut prices: Vec<(String, PriceInfo)> = vec![];
+
+    for asset in assets {
+        // Assets not registered are skipped, so that they don't fail the other prices of the batch
+        let asset_info = match ASSETS.may_load(deps.storage, asset.clone())? {
+            Some(asset_info) => asset_info,
+            None => continue,
+        };
+
+        // Frozen assets are skipped
+        if let Ok(Some(price)) = asset_info.local_price(&asset) {
+            prices.push((asset, price.clone()));
+        }
+    }
+
+    Ok(prices)
+}
+
+fn qy_remote_prices(deps: Deps, asset: String) -> Result<Vec<(String, PriceInfo)>, ContractError> {
+    Ok(REMOTE_PRICES
+        .prefix(asset)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, PriceInfo)>>>()?)
+}
+
+fn qy_feeders(deps: Deps, asset: String) -> Result<Vec<(Addr, Option<Submission>)>, ContractError> {
+    Ok(ASSETS
+        .load(deps.storage, asset.clone())?
+        .feeders
+        .into_iter()
+        .map(|feeder| {
+            let submission = SUBMISSIONS.may_load(deps.storage, (asset.clone(), feeder.clone()))?;
+            Ok((feeder, submission))
+        })
+        .collect::<StdResult<Vec<(Addr, Option<Submission>)>>>()?)
+}
+
+fn qy_twap(
+    deps: Deps,
+    env: Env,
+    asset: String,
+    window_seconds: u64,
+) -> Result<Decimal, ContractError> {
+    ASSETS
+        .load(deps.storage, asset.clone())?
+        .local_price(&asset)?;
+
+    let count = HISTORY_COUNT
+        .may_load(deps.storage, asset.clone())?
+        .unwrap_or_default();
+
+    let start = env.block.time.seconds().saturating_sub(window_seconds);
+
+    let mut end = env.block.time.seconds();
+    let mut weighted_sum = Decimal::zero();
+    let mut last_price: Option<Decimal> = None;
+
+    // From the newest price, until the first one before the window
+    for seq in (count.saturating_sub(HISTORY_SIZE)..count).rev() {
+        let info = HISTORY.load(deps.storage, (asset.clone(), seq % HISTORY_SIZE))?;
+
+        let from = info.time.seconds().max(start);
+
+        weighted_sum = weighted_sum
+            .checked_add(
+                info.price
+                    .checked_mul(Decimal::from_ratio(end.saturating_sub(from), 1_u64))
+                    .map_err(StdError::from)?,
+            )
+            .map_err(StdError::from)?;
+
+        end = from;
+        last_price = Some(info.price);
+
+        if info.time.seconds() <= start {
+            break;
+        }
+    }
+
+    let last_price = last_price.ok_or(ContractError::PriceNeverFeeded {})?;
+
+    let duration = env.block.time.seconds() - end;
+
+    if duration == 0 {
+        return Ok(last_price);
+    }
+
+    Ok(weighted_sum / Uint128::from(duration))
+}
+
+fn qy_history(
+    deps: Deps,
+    asset: String,
+    start_after: Option<u64>,
+    limit: Option<u64>,
+) -> Result<Vec<(u64, PriceInfo)>, ContractError> {
+    let limit = match limit {
+        Some(value) => min(value, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+
+    let count = HISTORY_COUNT
+        .may_load(deps.storage, asset.clone())?
+        .unwrap_or_default();
+
+    let first = match start_after {
+        Some(start_after) => start_after.saturating_add(1),
+        None => 0,
+    }
+    .max(count.saturating_sub(HISTORY_SIZE));
+
+    Ok((first..min(first.saturating_add(limit), count))
+        .map(|seq| {
+            Ok((
+                seq,
+                HISTORY.load(deps.storage, (asset.clone(), seq % HISTORY_SIZE))?,
+            ))
+        })
+        .collect::<StdResult<Vec<(u64, PriceInfo)>>>()?)
+}
+
+fn qy_subscriptions(
+    deps: Deps,
+    start_after: Option<(String, String, String)>,
+    limit: Option<u64>,
+) -> StdResult<Vec<(SubscriptionKey, Subscription)>> {
+    let limit = match limit {
+        Some(value) => min(value, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+
+    let start_after = start_after
+        .map(|(asset, chain, subscriber)| -> StdResult<_> {
+            Ok((asset, chain, deps.api.addr_validate(&subscriber)?))
+        })
+        .transpose()?;
+
+    SUBSCRIPTIONS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .collect()
+}
+
+fn qy_pending_requests(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u64>,
+) -> StdResult<Vec<(u64, PendingRequest)>> {
+    let limit = match limit {
+        Some(value) => min(value, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+
+    PENDING_REQUESTS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .collect()
+}
+
+fn qy_assets(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u64>,
+) -> Result<Vec<(String, Asset)>, ContractError> {
+    let limit = match limit {
+        Some(value) => min(value, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+
+    Ok(ASSETS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .collect::<StdResult<Vec<(String, Asset)>>>()?)
+}
+
+fn qy_prices(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u64>,
+) -> Result<Vec<(String, PriceInfo)>, ContractError> {
+    let limit = match limit {
+        Some(value) => min(value, MAX_LIMIT),
+        None => DEFAULT_LIMIT,
+    };
+
+    let start: Option<Bound<String>> = start_after.map(Bound::exclusive);
+
+    let mut prices: Vec<(String, PriceInfo)> = vec![];
+
+    for item in ASSETS.range(deps.storage, start, None, Order::Ascending) {
+        if prices.len() as u64 == limit {
+            break;
+        }
+
+        let (asset, info) = item?;
+
+        // Assets never feeded or frozen are skipped
+        if let Ok(Some(price)) = info.local_price(&asset) {
+            prices.push((asset, price.clone()));
+        }
+    }
+
+    Ok(prices)
+}
+
+// --- FUNCTIONS ---
+
+/// Check that `feed_config` can be used with `feeders`, that must be unique.
+fn validate_feed_config(feed_config: &FeedConfig, feeders: &[Addr]) -> Result<(), ContractError> {
+    for (i, feeder) in feeders.iter().enumerate() {
+        if feeders[..i].contains(feeder) {
+            return Err(ContractError::DuplicateFeeder {
+                feeder: feeder.to_string(),
+            });
+        }
+    }
+
+    if feed_config.quorum == 0 || feed_config.quorum as usize > feeders.len() {
+        return Err(ContractError::InvalidQuorum {
+            quorum: feed_config.quorum,
+            feeders: feeders.len() as u32,
+        });
+    }
+
+    if let Aggregation::TrimmedMean { trim } = feed_config.aggregation {
+        if trim * 2 >= feed_config.quorum {
+            return Err(ContractError::InvalidTrim {
+                quorum: feed_config.quorum,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the pair of `asset` from the one in `old` metadata to the one in `new` metadata.
+fn update_pair(
+    storage: &mut dyn Storage,
+    asset: &str,
+    old: Option<&AssetMetadata>,
+    new: Option<&AssetMetadata>,
+) -> Result<(), ContractError> {
+    if let Some(old) = old {
+        PAIRS.remove(storage, (old.base_symbol.clone(), old.quote_symbol.clone()));
+    }
+
+    if let Some(new) = new {
+        let key = (new.base_symbol.clone(), new.quote_symbol.clone());
+
+        if let Some(registered) = PAIRS.may_load(storage, key.clone())? {
+            return Err(ContractError::PairAlredyRegistered {
+                base: key.0,
+                quote: key.1,
+                asset: registered,
+            });
+        }
+
+        PAIRS.save(storage, key, &asset.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Return `false` if `price` violates `guard`, given the `previous` price.
+fn check_guard(guard: &PriceGuard, previous: Option<&PriceInfo>, price: Decimal) -> bool {
+    if price.is_zero() {
+        return false;
+    }
+
+    if guard.min_price.is_some_and(|min_price| price < min_price)
+        || guard.max_price.is_some_and(|max_price| price > max_price)
+    {
+        return false;
+    }
+
+    match (guard.max_deviation, previous) {
+        // A deviation too large to be represented is a violation
+        (Some(max_deviation), Some(previous)) if !previous.price.is_zero() => price
+            .abs_diff(previous.price)
+            .checked_div(previous.price)
+            .is_ok_and(|deviation| deviation <= max_deviation),
+        _ => true,
+    }
+}
+
+/// Aggregate the submitted `prices`, that can't be empty.
+pub(crate) fn aggregate_prices(
+    mut prices: Vec<Decimal>,
+    aggregation: &Aggregation,
+) -> StdResult<Decimal> {
+    prices.sort();
+
+    let len = prices.len();
+
+    match aggregation {
+        Aggregation::Median {} => {
+            if len % 2 == 1 {
+                Ok(prices[len / 2])
+            } else {
+                Ok((prices[len / 2 - 1] + prices[len / 2]) / Uint128::from(2_u128))
+            }
+        }
+        Aggregation::TrimmedMean { trim } => {
+            let trim = min(*trim as usize, (len - 1) / 2);
+
+            let trimmed = &prices[trim..len - trim];
+
+            let sum = trimmed
+                .iter()
+                .try_fold(Decimal::zero(), |sum, price| sum.checked_add(*price))?;
+
+            Ok(sum / Uint128::from(trimmed.len() as u128))
+        }
+    }
+}
+
+/// Save a `PendingRequest` for the prices of `assets` from `chain` and build its gate request.
+fn request_remote_prices(
+    storage: &mut dyn Storage,
+    env: &Env,
+    assets: Vec<String>,
+    chain: String,
+    funds: Vec<Coin>,
+    callback: Option<PriceCallback>,
+) -> Result<CosmosMsg, ContractError> {
+    let request_id = REQUEST_ID.may_load(storage)?.unwrap_or_default() + 1;
+
+    REQUEST_ID.save(storage, &request_id)?;
+
+    let request = PendingRequest {
+        assets,
+        chain,
+        callback,
+        status: RequestStatus::Pending {},
+        sent: env.block.time,
+        attempts: 1,
+    };
+
+    PENDING_REQUESTS.save(storage, request_id, &request)?;
+
+    remote_prices_msg(storage, request_id, &request, funds)
+}
+
+/// Build the gate request that query the prices of the assets of `request`, with their metadata, from the oracle of its chain.
+/// A legacy oracle is sent a `Price` query for every asset.
+fn remote_prices_msg(
+    storage: &dyn Storage,
+    request_id: u64,
+    request: &PendingRequest,
+    funds: Vec<Coin>,
+) -> Result<CosmosMsg, ContractError> {
+    let remote_contract = load_chain_contracts(storage, &request.chain)?.remove(0);
+
+    let legacy = CHAINS_LEGACY_CONTRACT
+        .may_load(storage, request.chain.clone())?
+        .unwrap_or_default()
+        .contains(&remote_contract);
+
+    let msgs = if legacy {
+        request
+            .assets
+            .iter()
+            .map(|asset| {
+                to_binary(&LegacyQueryMsg::Price {
+                    asset: asset.clone(),
+                })
+            })
+            .collect::<StdResult<Vec<Binary>>>()?
+    } else {
+        vec![to_binary(&QueryMsg::PricesByList {
+            assets: request.assets.clone(),
+        })?]
+    };
+
+    let queries = msgs
+        .into_iter()
+        .map(|msg| {
+            QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: remote_contract.clone(),
+                msg,
+            })
+        })
+        .collect();
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: GATE.load(storage)?.to_string(),
+
+        msg: to_binary(&GateExecuteMsg::SendRequests {
+            requests: vec![GateRequest::Query {
+                queries,
+                callback_msg: Some(to_binary(&RequestCallback { request_id })?),
+            }],
+            chain: request.chain.clone(),
+            timeout: None,
+        })?,
+
+        funds,
+    }))
+}
+
+/// Add `funds` to the balance of `subscription`.
+/// Only one coin, in the denom of the balance or of the `RefreshFees`, can be sent.
+fn add_subscription_funds(
+    storage: &dyn Storage,
+    subscription: &mut Subscription,
+    funds: Vec<Coin>,
+) -> Result<(), ContractError> {
+    if funds.is_empty() {
+        return Ok(());
+    }
+
+    let denom = match &subscription.balance {
+        Some(balance) => Some(balance.denom.clone()),
+        None => REFRESH_FEES.may_load(storage)?.map(|fees| fees.denom),
+    };
+
+    match (denom, funds.as_slice()) {
+        (Some(denom), [coin]) if coin.denom == denom => {
+            let balance = subscription.balance.get_or_insert(Coin {
+                denom,
+                amount: Uint128::zero(),
+            });
+
+            balance.amount = balance
+                .amount
+                .checked_add(coin.amount)
+                .map_err(StdError::from)?;
+
+            Ok(())
+        }
+        _ => Err(ContractError::InvalidFunds {}),
+    }
+}
+
+/// Split every coin of `funds` evenly in `parts`. The remainder goes to the first part.
+fn split_funds(funds: Vec<Coin>, parts: usize) -> Vec<Vec<Coin>> {
+    let mut splitted: Vec<Vec<Coin>> = vec![vec![]; parts];
+
+    for coin in funds {
+        let amount = coin.amount / Uint128::from(parts as u128);
+        let remainder = coin.amount - amount * Uint128::from(parts as u128);
+
+        for (i, part) in splitted.iter_mut().enumerate() {
+            let amount = if i == 0 { amount + remainder } else { amount };
+
+            if !amount.is_zero() {
+                part.push(Coin {
+                    denom: coin.denom.clone(),
+                    amount,
+                });
+            }
+        }
+    }
+
+    splitted
+}
+
+/// Save `price` for `asset`, with the current block as update time, and push it in the history.
+fn save_price(
+    storage: &mut dyn Storage,
+    env: &Env,
+    asset: &str,
+    price: Decimal,
+    source: PriceSource,
+) -> Result<(), ContractError> {
+    let info = PriceInfo {
+        price,
+        time: env.block.time,
+        height: env.block.height,
+        source,
+    };
+
+    ASSETS.update(
+        storage,
+        asset.to_string(),
+        |asset| -> Result<Asset, StdError> {
+            let mut asset = asset.ok_or(StdError::not_found("Asset"))?;
+
+            asset.price = Some(info.clone());
+
+            Ok(asset)
+        },
+    )?;
+
+    let count = HISTORY_COUNT
+        .may_load(storage, asset.to_string())?
+        .unwrap_or_default();
+
+    HISTORY.save(storage, (asset.to_string(), count % HISTORY_SIZE), &info)?;
+
+    HISTORY_COUNT.save(storage, asset.to_string(), &(count + 1))?;
+
+    Ok(())
+}
+
+/// Save `price` for `asset` fetched from the oracle of `chain` and return it. The local price is not changed.
+/// If `remote_time` is specified, it's used as update time, so the age of the price is the one on the remote chain.
+/// Return `None` without saving if the stored price is newer, received before an update sent later.
+fn save_remote_price(
+    storage: &mut dyn Storage,
+    env: &Env,
+    asset: &str,
+    chain: &str,
+    price: Decimal,
+    remote_time: Option<Timestamp>,
+) -> Result<Option<PriceInfo>, ContractError> {
+    if !ASSETS.has(storage, asset.to_string()) {
+        return Err(StdError::not_found("Asset").into());
+    }
+
+    let info = PriceInfo {
+        price,
+        time: remote_time.map_or(env.block.time, |time| min(time, env.block.time)),
+        height: env.block.height,
+        source: PriceSource::Remote {
+            chain: chain.to_string(),
+        },
+    };
+
+    let key = (asset.to_string(), chain.to_string());
+
+    if REMOTE_PRICES
+        .may_load(storage, key.clone())?
+        .is_some_and(|stored| stored.time > info.time)
+    {
+        return Ok(None);
+    }
+
+    REMOTE_PRICES.save(storage, key, &info)?;
+
+    Ok(Some(info))
+}
+
+/// Fails if `max_age` is specified and `price` is older than `max_age` seconds.
+fn check_age(
+    env: &Env,
+    asset: &str,
+    price: &PriceInfo,
+    max_age: Option<u64>,
+) -> Result<(), ContractError> {
+    if let Some(max_age) = max_age {
+        let age = env
+            .block
+            .time
+            .seconds()
+            .saturating_sub(price.time.seconds());
+
+        if age > max_age {
+            return Err(ContractError::PriceTooOld {
+                asset: asset.to_string(),
+                age,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the chain where `remote_contract` is registered.
+fn find_chain(storage: &dyn Storage, remote_contract: &str) -> Result<String, ContractError> {
+    for item in CHAINS_CONTRACT.range(storage, None, None, Order::Ascending) {
+        let (chain, contracts) = item?;
+
+        if contracts.iter().any(|contract| contract == remote_contract) {
+            return Ok(chain);
+        }
+    }
+
+    Err(ContractError::Unauthorized {})
+}
+
+/// Return the remote oracles registered for `chain`, failing if the chain is not registered.
+fn load_chain_contracts(storage: &dyn Storage, chain: &str) -> Result<Vec<String>, ContractError> {
+    CHAINS_CONTRACT
+        .may_load(storage, chain.to_string())?
+        .ok_or(ContractError::ChainNotRegistered {
+            chain: chain.to_string(),
+        })
+}
+
+/// Build the `SetPermission` msg for `gate`, from the remote oracles and the `PermissionMode` of `chain`.
+/// If `chain` is not registered, the permission to any remote contract is revoked.
+fn gate_set_permission_msg(storage: &dyn Storage, chain: String) -> StdResult<CosmosMsg> {
+    let permission = match (
+        CHAINS_CONTRACT.may_load(storage, chain.clone())?,
+        CHAINS_PERMISSION
+            .may_load(storage, chain.clone())?
+            .unwrap_or_default(),
+    ) {
+        (Some(_), PermissionMode::Permissionless {}) => Permission::Permissionless {},
+        (contracts, _) => Permission::Permissioned {
+            addresses: contracts.unwrap_or_default(),
+        },
+    };
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: GATE.load(storage)?.to_string(),
+        msg: to_binary(&GateExecuteMsg::SetPermission { permission, chain })?,
+        funds: vec![],
+    }))
+}
+
+/// Build the msg that refunds the balance left in `subscription` to the subscriber.
+fn refund_subscription_msg(subscription: Subscription) -> Option<CosmosMsg> {
+    subscription
+        .balance
+        .filter(|balance| !balance.amount.is_zero())
+        .map(|balance| {
+            BankMsg::Send {
+                to_address: subscription.subscriber.to_string(),
+                amount: vec![balance],
+            }
+            .into()
+        })
+}
 
 fn onlyowner(storage: &dyn Storage, address: &Addr) -> Result<(), ContractError> {
     if OWNER.load(storage)? != *address {
@@ -306,7 +2328,11 @@ fn onlyowner(storage: &dyn Storage, address: &Addr) -> Result<(), ContractError>
 }
 
 fn onlyfeeder(storage: &dyn Storage, feeder: &Addr, asset: &String) -> Result<(), ContractError> {
-    if ASSETS.load(storage, asset.to_owned())?.feeder != *feeder {
+    if !ASSETS
+        .load(storage, asset.to_owned())?
+        .feeders
+        .contains(feeder)
+    {
         return Err(ContractError::Unauthorized {});
     }
     Ok(())
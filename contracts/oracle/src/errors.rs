@@ -12,6 +12,73 @@ pub enum ContractError {
     #[error("Asset {asset} alredy registered")]
     AssetAlredyRegistered { asset: String },
 
+    #[error("Invalid quorum {quorum} for {feeders} feeders")]
+    InvalidQuorum { quorum: u32, feeders: u32 },
+
+    #[error("Trimmed mean discards all the {quorum} submissions of the quorum")]
+    InvalidTrim { quorum: u32 },
+
+    #[error("Feeder {feeder} not found")]
+    FeederNotFound { feeder: String },
+
+    #[error("Feeder {feeder} registered more than once")]
+    DuplicateFeeder { feeder: String },
+
+    #[error("No {name} specified")]
+    EmptyList { name: String },
+
+    #[error("No remote oracle of {chain} can receive the pushed prices")]
+    PushNotSupported { chain: String },
+
+    #[error("Invalid funds, only one coin in the denom of the refresh fees can be sent")]
+    InvalidFunds {},
+
+    #[error("No subscription is due")]
+    NothingDue {},
+
+    #[error("Invalid min interval, it has to be lower or equal to {max} seconds")]
+    InvalidMinInterval { max: u64 },
+
+    #[error("Pair {base}/{quote} alredy priced by {asset}")]
+    PairAlredyRegistered {
+        base: String,
+        quote: String,
+        asset: String,
+    },
+
+    #[error("Min price greater than max price")]
+    InvalidPriceGuard {},
+
+    #[error("Price {price} of {asset} violates the guard")]
+    PriceOutOfBounds { asset: String, price: String },
+
+    #[error("Asset {asset} is frozen")]
+    AssetFrozen { asset: String },
+
+    #[error("Asset {asset} is not frozen")]
+    AssetNotFrozen { asset: String },
+
+    #[error("Request {request_id} is not failed")]
+    RequestNotFailed { request_id: u64 },
+
+    #[error("Price of {asset} too old, updated {age} seconds ago")]
+    PriceTooOld { asset: String, age: u64 },
+
+    #[error("No path from {base} to {quote}")]
+    NoPath { base: String, quote: String },
+
+    #[error("Invalid price of {asset}")]
+    InvalidPrice { asset: String },
+
+    #[error("Chain {chain} not registered")]
+    ChainNotRegistered { chain: String },
+
+    #[error("Remote contract {contract} alredy registered for chain {chain}")]
+    RemoteContractAlredyRegistered { contract: String, chain: String },
+
+    #[error("Remote contract {contract} not registered for chain {chain}")]
+    RemoteContractNotRegistered { contract: String, chain: String },
+
     #[error("Price never feeded")]
     PriceNeverFeeded {},
 }
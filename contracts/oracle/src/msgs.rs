@@ -1,36 +1,348 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Decimal};
+use cosmwasm_std::{Addr, Binary, Decimal};
+use enum_repr::EnumRepr;
 use gate_pkg::GateMsg;
 
+use crate::state::{
+    Asset, AssetMetadata, FeedConfig, Frozen, PendingRequest, PermissionMode, PriceGuard,
+    PriceInfo, RefreshFees, Submission, Subscription, SubscriptionKey,
+};
+
 #[cw_serde]
 pub struct InstantiateMsg {}
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    RegisterAsset { asset: String, feeder: Addr },
+    /// Register `asset`, with its set of `feeders`. The default `FeedConfig` publishes every submission.
+    RegisterAsset {
+        asset: String,
+        feeders: Vec<Addr>,
+        feed_config: Option<FeedConfig>,
+        metadata: Option<AssetMetadata>,
+    },
+
+    /// Replace `old_feeder` with `new_feeder`, discarding the submission of `old_feeder`.
+    UpdateFeeder {
+        asset: String,
+        old_feeder: Addr,
+        new_feeder: Addr,
+    },
+
+    SetAssetMetadata {
+        asset: String,
+        metadata: Option<AssetMetadata>,
+    },
 
-    FeedPrice { asset: String, price: Decimal },
+    /// Remove `asset` with its prices and history.
+    /// The subscriptions of the asset are removed, sending the remaining balance to the subscribers.
+    RemoveAsset {
+        asset: String,
+    },
+
+    SetFeedConfig {
+        asset: String,
+        feed_config: FeedConfig,
+    },
+
+    SetPriceGuard {
+        asset: String,
+        guard: PriceGuard,
+    },
+
+    /// Unfreeze `asset`, publishing `price` if specified.
+    ResolveFrozen {
+        asset: String,
+        price: Option<Decimal>,
+    },
+
+    /// Submit the price of a feeder. The price is published when a quorum of fresh submissions is reached.
+    FeedPrice {
+        asset: String,
+        price: Decimal,
+    },
 
     // Remote iteration
-    FeedRemotePrice { asset: String, chain: String },
+    FeedRemotePrice {
+        asset: String,
+        chain: String,
+    },
+
+    /// Fetch the prices of all the `assets` from `chain` with a single gate request.
+    FeedRemotePrices {
+        assets: Vec<String>,
+        chain: String,
+    },
+
+    /// Fetch the prices of all the `assets` from every chain in `chains`.
+    /// The funds are split evenly between the chains, the remainder goes to the first one.
+    FeedRemotePricesMultiChain {
+        assets: Vec<String>,
+        chains: Vec<String>,
+    },
+
+    /// Push the local prices of `assets` to all the remote oracles of every chain in `chains`.
+    /// The legacy oracles, that can't receive the prices, are skipped.
+    /// Assets not registered, never feeded or frozen are skipped. Anyone can push, paying the gate fees.
+    /// The funds are split evenly between the chains, the remainder goes to the first one.
+    PushPrices {
+        assets: Vec<String>,
+        chains: Vec<String>,
+    },
+
+    /// Fetch the price of `asset` from `chain`. When the price is received,
+    /// the sender is called back with `PriceReceiverMsg::RemotePriceCallback`.
+    /// The price is saved also if the callback fails. No callback is sent if a newer price of `chain` was received in the meantime.
+    RequestRemotePrice {
+        asset: String,
+        chain: String,
+        callback: Binary,
+    },
+
+    /// Send again a failed remote price request, with the funds for the gate.
+    RetryRequest {
+        request_id: u64,
+    },
+
+    // Subscriptions
+    /// Set the fees paid by the subscriptions for every refresh. With `None`, refreshes are free.
+    SetRefreshFees {
+        fees: Option<RefreshFees>,
+    },
+
+    /// Subscribe to the refresh of the price of `asset` from `chain`, at most every `min_interval` seconds.
+    /// The funds sent are added to the balance of the subscription.
+    Subscribe {
+        asset: String,
+        chain: String,
+        min_interval: u64,
+    },
+
+    /// Add the funds sent to the balance of the subscription of `subscriber`.
+    /// The balance left is refunded to the subscriber on `Unsubscribe`.
+    FundSubscription {
+        asset: String,
+        chain: String,
+        subscriber: String,
+    },
+
+    /// Remove the subscription of the sender, sending the remaining balance to the subscriber.
+    /// The owner can remove the subscription of any `subscriber`.
+    Unsubscribe {
+        asset: String,
+        chain: String,
+        subscriber: Option<String>,
+    },
+
+    /// Refresh every subscription that is due, paying the keeper from the subscriptions balance.
+    /// A pair due for several subscribers is refreshed once, paid by the first one with enough balance.
+    RefreshDue {},
 
     // Gate permission and registration
-    RegisterGate { contract: Addr },
+    RegisterGate {
+        contract: Addr,
+    },
 
-    GateSetPermission { contract: String, chain: String },
+    /// Add a remote oracle to the list of contracts allowed to send msgs from `chain`.
+    GateSetPermission {
+        contract: String,
+        chain: String,
+    },
+    /// Remove a remote oracle from the list of contracts allowed to send msgs from `chain`.
+    /// The chain is removed with its last remote oracle.
+    GateRemovePermission {
+        contract: String,
+        chain: String,
+    },
+    /// Set which remote contracts the `gate` allows to send msgs from `chain`.
+    GateSetPermissionMode {
+        chain: String,
+        mode: PermissionMode,
+    },
+    /// Set if a remote oracle of `chain` only supports the `Price` query.
+    /// Its prices are then requested one asset at a time, without the time of the remote update.
+    SetLegacyContract {
+        contract: String,
+        chain: String,
+        legacy: bool,
+    },
+    /// Remove `chain`, revoking the permission on `gate`.
+    /// The remote prices fetched from `chain` are removed and its subscriptions refunded.
+    RemoveChain {
+        chain: String,
+    },
 
     // Gate msg receive implementation
     ReceiveGateMsg(GateMsg),
 }
 
+#[cw_serde]
+pub struct CrossRateResponse {
+    pub price: Decimal,
+    /// Pairs used to derive the price, from `base` to `quote`.
+    pub path: Vec<PathStep>,
+}
+
+/// Response of `QueryMsg::Price`, serialized as the bare price for the registered assets.
+#[cw_serde]
+#[serde(untagged)]
+pub enum PriceResponse {
+    Price(Decimal),
+    /// Rate derived for a not registered `BASE/QUOTE`, with its path.
+    CrossRate(CrossRateResponse),
+}
+
+#[cw_serde]
+pub struct PathStep {
+    pub asset: String,
+    /// `true` if the asset prices the pair in the opposite direction, so its inverse is used.
+    pub inverted: bool,
+    pub price: PriceInfo,
+}
+
+#[cw_serde]
+pub struct ChainResponse {
+    pub chain: String,
+    pub contracts: Vec<String>,
+    pub mode: PermissionMode,
+    /// Remote oracles that only support the `Price` query.
+    pub legacy_contracts: Vec<String>,
+}
+
+#[cw_serde]
+pub struct GuardResponse {
+    pub guard: PriceGuard,
+    pub frozen: Option<Frozen>,
+}
+
+/// Msg executed on the sender of a `RequestRemotePrice`.
+#[cw_serde]
+pub enum PriceReceiverMsg {
+    RemotePriceCallback {
+        asset: String,
+        chain: String,
+        price: PriceInfo,
+        /// `callback` specified in the `RequestRemotePrice`.
+        msg: Binary,
+    },
+}
+
+/// Msg sent to the remote oracles with `GateRequest::SendMsg`.
+#[cw_serde]
+pub enum OracleGateMsg {
+    /// Local prices of the sender, saved as remote prices by the receiver.
+    PriceUpdate { prices: Vec<(String, PriceInfo)> },
+}
+
+/// `callback_msg` of the remote price requests sent to the gate.
+#[cw_serde]
+pub struct RequestCallback {
+    pub request_id: u64,
+}
+
+/// Price query of the remote oracles that only support the `Price` query.
+#[cw_serde]
+pub enum LegacyQueryMsg {
+    Price { asset: String },
+}
+
+/// Which price of an asset is returned.
+#[cw_serde]
+pub enum PriceSelector {
+    /// Price published by the local feeders.
+    Local {},
+    /// Price fetched from the oracle of `chain`.
+    Remote { chain: String },
+    /// Median of the local and the remote prices. With `max_age`, only the fresh ones are considered.
+    Aggregated {},
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
+    /// Return the price of `asset`, by default the local one. If `max_age` is specified, fails if the price is older than `max_age` seconds.
+    /// If `asset` is not registered and has the form `BASE/QUOTE`, the cross rate is derived from the local prices of the pairs
+    /// and returned with its path.
+    #[returns(PriceResponse)]
+    Price {
+        asset: String,
+        max_age: Option<u64>,
+        selector: Option<PriceSelector>,
+    },
+    /// Return the rate of `base` in `quote`, derived routing through the pairs of the registered assets.
+    /// At most `max_path_length` pairs are used, capped to `MAX_PATH_LENGTH`.
+    #[returns(CrossRateResponse)]
+    CrossRate {
+        base: String,
+        quote: String,
+        max_age: Option<u64>,
+        max_path_length: Option<u32>,
+    },
+    /// Return the local price of `asset` with the update metadata.
+    #[returns(PriceInfo)]
+    PriceWithMeta { asset: String },
+    /// Return the local prices of `assets` with the update metadata. Assets not registered or never feeded are skipped.
+    #[returns(Vec<(String, PriceInfo)>)]
+    PricesByList { assets: Vec<String> },
+    /// Return the prices of `asset` fetched from the remote chains, with the chain.
+    #[returns(Vec<(String, PriceInfo)>)]
+    RemotePrices { asset: String },
+    #[returns(Asset)]
+    AssetInfo { asset: String },
+    #[returns(Vec<(String, Asset)>)]
+    Assets {
+        start_after: Option<String>,
+        limit: Option<u64>,
+    },
+    #[returns(GuardResponse)]
+    Guard { asset: String },
+    /// Return the last submission of every feeder of `asset`.
+    #[returns(Vec<(Addr, Option<Submission>)>)]
+    Feeders { asset: String },
+    /// Return the time weighted average price of `asset` over the last `window_seconds`.
+    /// The price before the window is used from the start of the window.
     #[returns(Decimal)]
-    Price { asset: String },
-    #[returns(Vec<(String, Decimal)>)]
+    Twap { asset: String, window_seconds: u64 },
+    /// Return the last prices of `asset`, with their sequence number, from the oldest.
+    #[returns(Vec<(u64, PriceInfo)>)]
+    History {
+        asset: String,
+        start_after: Option<u64>,
+        limit: Option<u64>,
+    },
+    #[returns(ChainResponse)]
+    Chain { chain: String },
+    #[returns(Vec<ChainResponse>)]
+    Chains {
+        start_after: Option<String>,
+        limit: Option<u64>,
+    },
+    #[returns(Option<RefreshFees>)]
+    RefreshFees {},
+    /// Return the subscriptions, keyed by `(asset, chain, subscriber)`.
+    #[returns(Vec<(SubscriptionKey, Subscription)>)]
+    Subscriptions {
+        start_after: Option<(String, String, String)>,
+        limit: Option<u64>,
+    },
+    /// Return the remote price requests not answered yet, including the failed ones.
+    #[returns(Vec<(u64, PendingRequest)>)]
+    PendingRequests {
+        start_after: Option<u64>,
+        limit: Option<u64>,
+    },
+    /// Return the prices with the update metadata. Assets never feeded are skipped.
+    #[returns(Vec<(String, PriceInfo)>)]
     Prices {
         start_after: Option<String>,
         limit: Option<u64>,
     },
 }
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[EnumRepr(type = "u64")]
+pub enum MsgReplyID {
+    /// `PriceReceiverMsg::RemotePriceCallback` sent to a consumer, replied only on error.
+    PriceCallback = 1,
+}
@@ -1,16 +1,233 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Decimal};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Timestamp, Uint128};
 use cw_storage_plus::{Item, Map};
 
+use crate::errors::ContractError;
+
 // --- CONSTANTS ---
 pub const OWNER: Item<Addr> = Item::new("owner");
 pub const ASSETS: Map<String, Asset> = Map::new("assets");
+/// Asset that prices every `(base_symbol, quote_symbol)` pair, from the `AssetMetadata`.
+pub const PAIRS: Map<(String, String), String> = Map::new("pairs");
+/// Max number of pairs used to derive a cross rate.
+pub const MAX_PATH_LENGTH: u32 = 3;
+/// Last price submitted by every feeder, keyed by `(asset, feeder)`.
+pub const SUBMISSIONS: Map<(String, Addr), Submission> = Map::new("submissions");
+/// Ring buffer of the last `HISTORY_SIZE` prices of every asset, keyed by `(asset, slot)`.
+pub const HISTORY: Map<(String, u64), PriceInfo> = Map::new("history");
+/// Number of prices ever saved in the history of every asset.
+pub const HISTORY_COUNT: Map<String, u64> = Map::new("history_count");
+pub const HISTORY_SIZE: u64 = 100;
 
 pub const GATE: Item<Addr> = Item::new("gate");
-pub const CHAINS_CONTRACT: Map<String, String> = Map::new("chains_contracts");
+/// Remote oracles registered for every chain. The first one is queried for the remote prices.
+pub const CHAINS_CONTRACT: Map<String, Vec<String>> = Map::new("chains_contracts");
+pub const CHAINS_PERMISSION: Map<String, PermissionMode> = Map::new("chains_permission");
+/// Remote oracles of every chain that only support the `Price` query.
+pub const CHAINS_LEGACY_CONTRACT: Map<String, Vec<String>> = Map::new("chains_legacy_contracts");
+/// Last price fetched from the oracle of every chain, keyed by `(asset, chain)`.
+pub const REMOTE_PRICES: Map<(String, String), PriceInfo> = Map::new("remote_prices");
+/// Max `min_interval` of a subscription, one year.
+pub const MAX_MIN_INTERVAL: u64 = 31_536_000;
+/// Remote prices refreshed by the keepers, keyed by `(asset, chain, subscriber)`.
+pub const SUBSCRIPTIONS: Map<SubscriptionKey, Subscription> = Map::new("subscriptions");
+pub const REFRESH_FEES: Item<RefreshFees> = Item::new("refresh_fees");
+/// Remote price requests sent to the gate and not answered yet, keyed by request id.
+pub const PENDING_REQUESTS: Map<u64, PendingRequest> = Map::new("pending_requests");
+/// Id of the last remote price request.
+pub const REQUEST_ID: Item<u64> = Item::new("request_id");
 
 #[cw_serde]
 pub struct Asset {
-    pub feeder: Addr,
-    pub price: Option<Decimal>,
+    pub feeders: Vec<Addr>,
+    pub feed_config: FeedConfig,
+    pub guard: PriceGuard,
+    /// `Some` if a price violated the guard, until the owner resolves it.
+    pub frozen: Option<Frozen>,
+    pub metadata: Option<AssetMetadata>,
+    pub price: Option<PriceInfo>,
+}
+
+impl Asset {
+    /// Return the local price, failing if the asset is frozen.
+    pub fn local_price(&self, asset: &str) -> Result<Option<&PriceInfo>, ContractError> {
+        if self.frozen.is_some() {
+            return Err(ContractError::AssetFrozen {
+                asset: asset.to_string(),
+            });
+        }
+
+        Ok(self.price.as_ref())
+    }
+}
+
+#[cw_serde]
+pub struct AssetMetadata {
+    pub decimals: u8,
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub description: Option<String>,
+}
+
+/// Limits on the published prices of an asset. A zero price is always a violation.
+#[cw_serde]
+#[derive(Default)]
+pub struct PriceGuard {
+    /// Max deviation from the previous price, relative to it.
+    pub max_deviation: Option<Decimal>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    pub on_violation: GuardAction,
+}
+
+#[cw_serde]
+pub enum GuardAction {
+    /// The update is rejected.
+    Reject {},
+    /// The price is not published and the asset is frozen.
+    Freeze {},
+}
+
+impl Default for GuardAction {
+    fn default() -> Self {
+        GuardAction::Reject {}
+    }
+}
+
+/// Remote contracts allowed by the `gate` to send msgs from a chain.
+#[cw_serde]
+pub enum PermissionMode {
+    /// Only the remote oracles registered for the chain.
+    Permissioned {},
+    /// Any remote contract.
+    Permissionless {},
+}
+
+impl Default for PermissionMode {
+    fn default() -> Self {
+        PermissionMode::Permissioned {}
+    }
+}
+
+#[cw_serde]
+pub struct Frozen {
+    /// Price that violated the guard.
+    pub price: Decimal,
+    pub time: Timestamp,
+}
+
+/// How the submissions of the feeders are aggregated in the published price.
+#[cw_serde]
+pub struct FeedConfig {
+    /// Min number of fresh submissions required to publish a price.
+    pub quorum: u32,
+    /// Seconds a submission is considered fresh.
+    pub window: u64,
+    pub aggregation: Aggregation,
+    /// Max deviation from the published price, relative to it, before a feeder is flagged.
+    pub max_deviation: Option<Decimal>,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        FeedConfig {
+            quorum: 1,
+            window: 60,
+            aggregation: Aggregation::Median {},
+            max_deviation: None,
+        }
+    }
+}
+
+#[cw_serde]
+pub enum Aggregation {
+    Median {},
+    /// Mean of the submissions, discarding the `trim` lowest and the `trim` highest.
+    TrimmedMean {
+        trim: u32,
+    },
+}
+
+#[cw_serde]
+pub struct Submission {
+    pub price: Decimal,
+    pub time: Timestamp,
+    /// `true` if the submission deviated from the published price more than `max_deviation`.
+    pub flagged: bool,
+}
+
+/// `(asset, chain, subscriber)` of a subscription.
+pub type SubscriptionKey = (String, String, Addr);
+
+#[cw_serde]
+pub struct Subscription {
+    pub subscriber: Addr,
+    /// Min seconds between two refreshes.
+    pub min_interval: u64,
+    pub last_refresh: Option<Timestamp>,
+    /// Prefunded balance, used to pay the refreshes.
+    pub balance: Option<Coin>,
+}
+
+/// Paid by a `Subscription` for every refresh.
+#[cw_serde]
+pub struct RefreshFees {
+    pub denom: String,
+    /// Sent to the gate with the request.
+    pub gate_fee: Uint128,
+    /// Sent to the keeper that triggered the refresh.
+    pub keeper_reward: Uint128,
+}
+
+impl RefreshFees {
+    pub fn total(&self) -> Uint128 {
+        self.gate_fee + self.keeper_reward
+    }
+}
+
+#[cw_serde]
+pub struct PendingRequest {
+    pub assets: Vec<String>,
+    pub chain: String,
+    /// Receiver of the prices, for the requests sent by `RequestRemotePrice`.
+    pub callback: Option<PriceCallback>,
+    pub status: RequestStatus,
+    /// Time of the last attempt.
+    pub sent: Timestamp,
+    pub attempts: u32,
+}
+
+#[cw_serde]
+pub enum RequestStatus {
+    /// Waiting for the response of the remote chain.
+    Pending {},
+    /// The gate reported the request as failed or timed out. It can be retried.
+    Failed {},
+}
+
+/// Receiver of the remote prices requested with `RequestRemotePrice`.
+#[cw_serde]
+pub struct PriceCallback {
+    pub receiver: Addr,
+    pub msg: Binary,
+}
+
+#[cw_serde]
+pub struct PriceInfo {
+    pub price: Decimal,
+    /// Block time of the update.
+    pub time: Timestamp,
+    /// Block height of the update.
+    pub height: u64,
+    pub source: PriceSource,
+}
+
+#[cw_serde]
+pub enum PriceSource {
+    /// Price aggregated from the submissions of the `feeders`.
+    Local { feeders: Vec<Addr> },
+    /// Price fetched from the oracle of a remote chain.
+    Remote { chain: String },
+    /// Price set by the owner resolving a frozen asset.
+    Owner {},
 }
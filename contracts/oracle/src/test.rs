@@ -1,15 +1,27 @@
 use std::str::FromStr;
 
 use cosmwasm_std::{
-    from_binary,
+    coin, from_binary,
     testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier},
-    to_binary, Addr, Decimal, Env, MemoryStorage, MessageInfo, OwnedDeps, QueryRequest, WasmQuery,
+    to_binary, Addr, Attribute, BankMsg, Binary, Coin, CosmosMsg, Decimal, Empty, Env,
+    MemoryStorage, MessageInfo, OwnedDeps, QueryRequest, Reply, Response, SubMsg, SubMsgResult,
+    Uint128, WasmMsg, WasmQuery,
 };
-use gate_pkg::{GateMsg, GateQueryResponse};
+use cw_storage_plus::Map;
+use gate_pkg::{ExecuteMsg as GateExecuteMsg, GateMsg, GateQueryResponse, GateRequest, Permission};
 
 use crate::{
-    contract::{execute, instantiate, query},
-    msgs::{ExecuteMsg, InstantiateMsg, QueryMsg},
+    contract::{execute, instantiate, migrate, query, reply},
+    errors::ContractError,
+    msgs::{
+        ChainResponse, CrossRateResponse, ExecuteMsg, GuardResponse, InstantiateMsg, MigrateMsg,
+        MsgReplyID, OracleGateMsg, PriceReceiverMsg, PriceResponse, PriceSelector, QueryMsg,
+    },
+    state::{
+        Aggregation, Asset, AssetMetadata, FeedConfig, Frozen, GuardAction, PendingRequest,
+        PermissionMode, PriceGuard, PriceInfo, PriceSource, RefreshFees, RequestStatus, Submission,
+        Subscription, SubscriptionKey, CHAINS_CONTRACT, HISTORY_SIZE, SUBSCRIPTIONS,
+    },
 };
 
 fn register_and_feed(
@@ -24,7 +36,9 @@ fn register_and_feed(
 
     let msg = ExecuteMsg::RegisterAsset {
         asset: asset.to_string(),
-        feeder: info_feeder.sender.clone(),
+        feeders: vec![info_feeder.sender.clone()],
+        feed_config: None,
+        metadata: None,
     };
 
     let _res = execute(deps.as_mut(), env.clone(), info_owner, msg).unwrap();
@@ -63,6 +77,13 @@ fn main() {
 
     let _res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
 
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: remote_oracle_contract.to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    let _res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
     // REGISTER ASSET
 
     register_and_feed(
@@ -78,6 +99,8 @@ fn main() {
 
     let query_r: QueryMsg = QueryMsg::Price {
         asset: asset_name.clone(),
+        max_age: None,
+        selector: None,
     };
 
     let res = query(deps.as_ref(), env.clone(), query_r.clone()).unwrap();
@@ -107,7 +130,28 @@ fn main() {
 
     let _res = execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap();
 
-    let query_r: QueryMsg = QueryMsg::Price { asset: asset_name };
+    // The local price is not overwritten by the remote one
+
+    let query_r: QueryMsg = QueryMsg::Price {
+        asset: asset_name.clone(),
+        max_age: None,
+        selector: None,
+    };
+
+    let res = query(deps.as_ref(), env.clone(), query_r).unwrap();
+
+    assert_eq!(
+        Decimal::from_str("2").unwrap(),
+        from_binary::<Decimal>(&res).unwrap()
+    );
+
+    let query_r: QueryMsg = QueryMsg::Price {
+        asset: asset_name,
+        max_age: None,
+        selector: Some(PriceSelector::Remote {
+            chain: "remote_chain".to_string(),
+        }),
+    };
 
     let res = query(deps.as_ref(), env.clone(), query_r).unwrap();
 
@@ -158,7 +202,7 @@ fn main() {
 
     let res = query(deps.as_ref(), env.clone(), msg).unwrap();
 
-    let response: Vec<(String, Decimal)> = from_binary(&res).unwrap();
+    let response: Vec<(String, PriceInfo)> = from_binary(&res).unwrap();
 
     assert_eq!(3, response.len());
 
@@ -169,8 +213,2947 @@ fn main() {
 
     let res = query(deps.as_ref(), env, msg).unwrap();
 
-    let response: Vec<(String, Decimal)> = from_binary(&res).unwrap();
+    let response: Vec<(String, PriceInfo)> = from_binary(&res).unwrap();
 
     assert_eq!(2, response.len());
-    assert_eq!(Decimal::from_str("5").unwrap(), response.last().unwrap().1);
+    assert_eq!(
+        Decimal::from_str("5").unwrap(),
+        response.last().unwrap().1.price
+    );
+}
+
+#[test]
+fn price_metadata() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_oracle_contract".to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "atom".to_string(),
+        feeders: vec![feeder_info.sender.clone()],
+        feed_config: None,
+        metadata: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    // NEVER FEEDED ASSETS ARE SKIPPED
+
+    let prices = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>, env: &Env| {
+        from_binary::<Vec<(String, PriceInfo)>>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Prices {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    assert!(prices(&deps, &env).is_empty());
+
+    // LOCAL PRICE
+
+    let msg = ExecuteMsg::FeedPrice {
+        asset: "atom".to_string(),
+        price: Decimal::from_str("10").unwrap(),
+    };
+
+    execute(deps.as_mut(), env.clone(), feeder_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        prices(&deps, &env),
+        vec![(
+            "atom".to_string(),
+            PriceInfo {
+                price: Decimal::from_str("10").unwrap(),
+                time: env.block.time,
+                height: env.block.height,
+                source: PriceSource::Local {
+                    feeders: vec![feeder_info.sender],
+                },
+            }
+        )]
+    );
+
+    let price_with_meta: PriceInfo = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PriceWithMeta {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    // The assets not registered are skipped, without failing the batch
+    assert_eq!(
+        from_binary::<Vec<(String, PriceInfo)>>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::PricesByList {
+                    assets: vec!["juno".to_string(), "atom".to_string()],
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap(),
+        vec![("atom".to_string(), price_with_meta)]
+    );
+
+    // STALE PRICE
+
+    env.block.time = env.block.time.plus_seconds(60);
+    env.block.height += 10;
+
+    let price_query = |max_age: u64| QueryMsg::Price {
+        asset: "atom".to_string(),
+        max_age: Some(max_age),
+        selector: None,
+    };
+
+    query(deps.as_ref(), env.clone(), price_query(60)).unwrap();
+
+    let err = query(deps.as_ref(), env.clone(), price_query(59)).unwrap_err();
+
+    assert!(err.to_string().contains("too old"));
+
+    // REMOTE PRICE
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "remote_oracle_contract".to_string(),
+                msg: to_binary(&QueryMsg::Price {
+                    asset: "atom".to_string(),
+                    max_age: None,
+                    selector: None,
+                })
+                .unwrap(),
+            }),
+            response: to_binary(&Decimal::from_str("11").unwrap()).unwrap(),
+        }],
+        callback_msg: None,
+    });
+
+    execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap();
+
+    let (_, price) = prices(&deps, &env).pop().unwrap();
+
+    assert_eq!(price.price, Decimal::from_str("10").unwrap());
+
+    let (chain, price) = from_binary::<Vec<(String, PriceInfo)>>(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+    .pop()
+    .unwrap();
+
+    assert_eq!(chain, "remote_chain");
+    assert_eq!(price.time, env.block.time);
+    assert_eq!(price.height, env.block.height);
+    assert_eq!(
+        price.source,
+        PriceSource::Remote {
+            chain: "remote_chain".to_string()
+        }
+    );
+
+    // SELECTORS
+
+    let price_query = |max_age: Option<u64>, selector: PriceSelector| {
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                asset: "atom".to_string(),
+                max_age,
+                selector: Some(selector),
+            },
+        )
+        .map(|res| from_binary::<Decimal>(&res).unwrap())
+    };
+
+    assert_eq!(
+        price_query(
+            Some(0),
+            PriceSelector::Remote {
+                chain: "remote_chain".to_string()
+            }
+        )
+        .unwrap(),
+        Decimal::from_str("11").unwrap()
+    );
+
+    price_query(
+        None,
+        PriceSelector::Remote {
+            chain: "other_chain".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    price_query(Some(0), PriceSelector::Local {}).unwrap_err();
+
+    assert_eq!(
+        price_query(None, PriceSelector::Aggregated {}).unwrap(),
+        Decimal::from_str("10.5").unwrap()
+    );
+
+    // The stale local price is not aggregated
+    assert_eq!(
+        price_query(Some(0), PriceSelector::Aggregated {}).unwrap(),
+        Decimal::from_str("11").unwrap()
+    );
+}
+
+#[test]
+fn feeders_quorum() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeders: Vec<MessageInfo> = ["feeder000", "feeder001", "feeder002"]
+        .into_iter()
+        .map(|feeder| mock_info(feeder, &[]))
+        .collect();
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let register_msg =
+        |feeders: Vec<Addr>, quorum: u32, aggregation: Aggregation| ExecuteMsg::RegisterAsset {
+            asset: "atom".to_string(),
+            feeders,
+            feed_config: Some(FeedConfig {
+                quorum,
+                window: 60,
+                aggregation,
+                max_deviation: Some(Decimal::percent(10)),
+            }),
+            metadata: None,
+        };
+
+    let feeder_addrs: Vec<Addr> = feeders.iter().map(|info| info.sender.clone()).collect();
+
+    // INVALID CONFIGS
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        register_msg(feeder_addrs.clone(), 4, Aggregation::Median {}),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidQuorum { .. }));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        register_msg(
+            feeder_addrs.clone(),
+            2,
+            Aggregation::TrimmedMean { trim: 1 },
+        ),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidTrim { .. }));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        register_msg(
+            vec![feeder_addrs[0].clone(), feeder_addrs[0].clone()],
+            2,
+            Aggregation::Median {},
+        ),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::DuplicateFeeder { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        register_msg(feeder_addrs.clone(), 2, Aggregation::Median {}),
+    )
+    .unwrap();
+
+    let feed = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+                env: &Env,
+                feeder: usize,
+                price: u64| {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            feeders[feeder].clone(),
+            ExecuteMsg::FeedPrice {
+                asset: "atom".to_string(),
+                price: Decimal::from_atomics(price, 0).unwrap(),
+            },
+        )
+        .unwrap();
+    };
+
+    let price = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>, env: &Env| {
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                asset: "atom".to_string(),
+                max_age: None,
+                selector: None,
+            },
+        )
+        .map(|res| from_binary::<Decimal>(&res).unwrap())
+    };
+
+    // QUORUM
+
+    feed(&mut deps, &env, 0, 10);
+
+    assert!(price(&deps, &env).is_err());
+
+    feed(&mut deps, &env, 1, 12);
+
+    assert_eq!(
+        price(&deps, &env).unwrap(),
+        Decimal::from_atomics(11_u64, 0).unwrap()
+    );
+
+    // OLD SUBMISSIONS ARE NOT CONSIDERED
+
+    env.block.time = env.block.time.plus_seconds(61);
+
+    feed(&mut deps, &env, 2, 20);
+
+    assert_eq!(
+        price(&deps, &env).unwrap(),
+        Decimal::from_atomics(11_u64, 0).unwrap()
+    );
+
+    feed(&mut deps, &env, 0, 10);
+    feed(&mut deps, &env, 1, 11);
+
+    assert_eq!(
+        price(&deps, &env).unwrap(),
+        Decimal::from_atomics(11_u64, 0).unwrap()
+    );
+
+    // DEVIATING FEEDERS ARE FLAGGED
+
+    let submissions: Vec<(Addr, Option<Submission>)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Feeders {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    let flagged: Vec<bool> = submissions
+        .into_iter()
+        .map(|(_, submission)| submission.unwrap().flagged)
+        .collect();
+
+    assert_eq!(flagged, vec![false, false, true]);
+
+    // TRIMMED MEAN
+
+    let msg = ExecuteMsg::SetFeedConfig {
+        asset: "atom".to_string(),
+        feed_config: FeedConfig {
+            quorum: 3,
+            window: 60,
+            aggregation: Aggregation::TrimmedMean { trim: 1 },
+            max_deviation: None,
+        },
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    feed(&mut deps, &env, 1, 12);
+
+    assert_eq!(
+        price(&deps, &env).unwrap(),
+        Decimal::from_atomics(12_u64, 0).unwrap()
+    );
+
+    // A DEVIATION TOO LARGE TO BE REPRESENTED IS FLAGGED
+
+    let mut msg = register_msg(feeder_addrs.clone(), 1, Aggregation::Median {});
+
+    if let ExecuteMsg::RegisterAsset { asset, .. } = &mut msg {
+        *asset = "osmo".to_string();
+    }
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    for (feeder, price) in [
+        (2, "100000000000000000000"),
+        (0, "0.000000000000000001"),
+        (1, "0.000000000000000001"),
+    ] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            feeders[feeder].clone(),
+            ExecuteMsg::FeedPrice {
+                asset: "osmo".to_string(),
+                price: Decimal::from_str(price).unwrap(),
+            },
+        )
+        .unwrap();
+    }
+
+    let submissions: Vec<(Addr, Option<Submission>)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Feeders {
+                asset: "osmo".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(submissions[2].1.as_ref().unwrap().flagged);
+}
+
+#[test]
+fn history_and_twap() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let start = env.block.time;
+
+    register_and_feed(
+        &mut deps,
+        env.clone(),
+        owner_info,
+        feeder_info.clone(),
+        "atom".to_string(),
+        Decimal::from_atomics(10_u64, 0).unwrap(),
+    );
+
+    env.block.time = start.plus_seconds(100);
+
+    let msg = ExecuteMsg::FeedPrice {
+        asset: "atom".to_string(),
+        price: Decimal::from_atomics(20_u64, 0).unwrap(),
+    };
+
+    execute(deps.as_mut(), env.clone(), feeder_info.clone(), msg).unwrap();
+
+    env.block.time = start.plus_seconds(200);
+
+    let twap = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>, window_seconds: u64| {
+        from_binary::<Decimal>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Twap {
+                    asset: "atom".to_string(),
+                    window_seconds,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // The price of 20 is used for the whole window
+    assert_eq!(twap(&deps, 50), Decimal::from_atomics(20_u64, 0).unwrap());
+
+    // 50 seconds at 10 and 100 seconds at 20
+    assert_eq!(twap(&deps, 150), Decimal::from_ratio(50_u128, 3_u128));
+
+    // The window before the first price is not considered
+    assert_eq!(twap(&deps, 1000), Decimal::from_atomics(15_u64, 0).unwrap());
+
+    // The history is bounded to the last HISTORY_SIZE prices
+
+    for i in 0..HISTORY_SIZE {
+        env.block.time = env.block.time.plus_seconds(1);
+
+        let msg = ExecuteMsg::FeedPrice {
+            asset: "atom".to_string(),
+            price: Decimal::from_atomics(i + 1, 0).unwrap(),
+        };
+
+        execute(deps.as_mut(), env.clone(), feeder_info.clone(), msg).unwrap();
+    }
+
+    let history = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+                   start_after: Option<u64>| {
+        from_binary::<Vec<(u64, PriceInfo)>>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::History {
+                    asset: "atom".to_string(),
+                    start_after,
+                    limit: Some(5),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let res = history(&deps, None);
+
+    assert_eq!(res.len(), 5);
+    assert_eq!(res[0].0, 2);
+    assert_eq!(res[0].1.price, Decimal::one());
+
+    let res = history(&deps, Some(HISTORY_SIZE - 1));
+
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[1].0, HISTORY_SIZE + 1);
+    assert_eq!(
+        res[1].1.price,
+        Decimal::from_atomics(HISTORY_SIZE, 0).unwrap()
+    );
+
+    assert!(history(&deps, Some(u64::MAX)).is_empty());
+}
+
+#[test]
+fn feed_remote_prices() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[coin(101, "uLuna")]);
+    let gate_info = mock_info("gate_contract", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    for chain in ["chain_a", "chain_b"] {
+        let msg = ExecuteMsg::GateSetPermission {
+            contract: format!("oracle_{chain}"),
+            chain: chain.to_string(),
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    for asset in ["atom", "osmo"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            feeders: vec![feeder_info.sender.clone()],
+            feed_config: None,
+            metadata: None,
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    let assets = vec!["atom".to_string(), "osmo".to_string()];
+
+    // INVALID REQUESTS
+
+    let msg = ExecuteMsg::FeedRemotePrices {
+        assets: vec![],
+        chain: "chain_a".to_string(),
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), feeder_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::EmptyList { .. }));
+
+    let msg = ExecuteMsg::FeedRemotePrices {
+        assets: vec!["atom".to_string(), "luna".to_string()],
+        chain: "chain_a".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), feeder_info.clone(), msg).unwrap_err();
+
+    // ONE REQUEST PER CHAIN
+
+    let msg = ExecuteMsg::FeedRemotePricesMultiChain {
+        assets: assets.clone(),
+        chains: vec!["chain_a".to_string(), "chain_b".to_string()],
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), feeder_info, msg).unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+
+    let requests: Vec<(String, Vec<QueryRequest<Empty>>, Vec<Coin>)> = res
+        .messages
+        .into_iter()
+        .map(|msg| match msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) => {
+                match from_binary(&msg).unwrap() {
+                    GateExecuteMsg::SendRequests {
+                        mut requests,
+                        chain,
+                        ..
+                    } => match requests.pop().unwrap() {
+                        GateRequest::Query { queries, .. } => (chain, queries, funds),
+                        _ => panic!("Unexpected request"),
+                    },
+                    _ => panic!("Unexpected msg"),
+                }
+            }
+            _ => panic!("Unexpected msg"),
+        })
+        .collect();
+
+    // All the assets are requested with one query
+
+    assert_eq!(requests[0].0, "chain_a");
+    assert_eq!(
+        requests[0].1,
+        vec![QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: "oracle_chain_a".to_string(),
+            msg: to_binary(&QueryMsg::PricesByList {
+                assets: assets.clone()
+            })
+            .unwrap(),
+        })]
+    );
+    assert_eq!(requests[0].2, vec![coin(51, "uLuna")]);
+    assert_eq!(requests[1].0, "chain_b");
+    assert_eq!(requests[1].2, vec![coin(50, "uLuna")]);
+
+    // ALL THE PRICES ARE UPDATED BY THE RESPONSE
+
+    let (_, mut queries, _) = requests[1].clone();
+
+    // The prices were updated 30 seconds before on the remote chain
+    let remote_time = env.block.time.minus_seconds(30);
+
+    let remote_info = |price: &str| PriceInfo {
+        price: Decimal::from_str(price).unwrap(),
+        time: remote_time,
+        height: 1,
+        source: PriceSource::Local {
+            feeders: vec![Addr::unchecked("remote_feeder")],
+        },
+    };
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: queries.pop().unwrap(),
+            response: to_binary(&vec![
+                ("atom".to_string(), remote_info("3")),
+                ("osmo".to_string(), remote_info("4")),
+            ])
+            .unwrap(),
+        }],
+        callback_msg: None,
+    });
+
+    execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    let remote_prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(remote_prices[0].0, "chain_b");
+    assert_eq!(remote_prices[0].1.time, remote_time);
+    assert_eq!(remote_prices[0].1.height, env.block.height);
+
+    // PriceWithMeta responses are accepted too
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "oracle_chain_a".to_string(),
+                msg: to_binary(&QueryMsg::PriceWithMeta {
+                    asset: "atom".to_string(),
+                })
+                .unwrap(),
+            }),
+            response: to_binary(&remote_info("5")).unwrap(),
+        }],
+        callback_msg: None,
+    });
+
+    execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap();
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Price {
+            asset: "atom".to_string(),
+            max_age: Some(30),
+            selector: Some(PriceSelector::Remote {
+                chain: "chain_a".to_string(),
+            }),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        from_binary::<Decimal>(&res).unwrap(),
+        Decimal::from_str("5").unwrap()
+    );
+
+    for (asset, price) in assets.into_iter().zip(["3", "4"]) {
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                asset,
+                max_age: None,
+                selector: Some(PriceSelector::Remote {
+                    chain: "chain_b".to_string(),
+                }),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            from_binary::<Decimal>(&res).unwrap(),
+            Decimal::from_str(price).unwrap()
+        );
+    }
+}
+
+#[test]
+fn subscriptions() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+    let keeper_info = mock_info("keeper000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: Addr::unchecked("gate_contract"),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_oracle_contract".to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    for asset in ["atom", "osmo"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            feeders: vec![feeder_info.sender.clone()],
+            feed_config: None,
+            metadata: None,
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    // FEES
+
+    let msg = ExecuteMsg::SetRefreshFees {
+        fees: Some(RefreshFees {
+            denom: "uLuna".to_string(),
+            gate_fee: Uint128::from(10_u128),
+            keeper_reward: Uint128::from(5_u128),
+        }),
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), feeder_info, msg.clone()).unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    // SUBSCRIBE
+
+    let subscribe = |asset: &str| ExecuteMsg::Subscribe {
+        asset: asset.to_string(),
+        chain: "remote_chain".to_string(),
+        min_interval: 100,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        ExecuteMsg::Subscribe {
+            asset: "atom".to_string(),
+            chain: "remote_chain".to_string(),
+            min_interval: u64::MAX,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidMinInterval { .. }));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[coin(30, "uAtom")]),
+        subscribe("atom"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidFunds {}));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[coin(30, "uLuna")]),
+        subscribe("atom"),
+    )
+    .unwrap();
+
+    // Several subscribers for the same pair
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user001", &[]),
+        subscribe("atom"),
+    )
+    .unwrap();
+
+    // Not enough to pay a refresh
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user001", &[coin(10, "uLuna")]),
+        subscribe("osmo"),
+    )
+    .unwrap();
+
+    // REFRESH
+
+    let refresh = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, env: &Env| {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            keeper_info.clone(),
+            ExecuteMsg::RefreshDue {},
+        )
+    };
+
+    let res = refresh(&mut deps, &env).unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, funds, .. }) => {
+            assert_eq!(funds, &vec![coin(10, "uLuna")]);
+
+            match from_binary(msg).unwrap() {
+                GateExecuteMsg::SendRequests { requests, .. } => match &requests[0] {
+                    GateRequest::Query { queries, .. } => assert_eq!(queries.len(), 1),
+                    _ => panic!("Unexpected request"),
+                },
+                _ => panic!("Unexpected msg"),
+            }
+        }
+        _ => panic!("Unexpected msg"),
+    }
+
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: keeper_info.sender.to_string(),
+            amount: vec![coin(5, "uLuna")],
+        })
+    );
+
+    let err = refresh(&mut deps, &env).unwrap_err();
+
+    assert!(matches!(err, ContractError::NothingDue {}));
+
+    env.block.time = env.block.time.plus_seconds(100);
+
+    refresh(&mut deps, &env).unwrap();
+
+    // The balance of atom is over
+
+    env.block.time = env.block.time.plus_seconds(100);
+
+    let err = refresh(&mut deps, &env).unwrap_err();
+
+    assert!(matches!(err, ContractError::NothingDue {}));
+
+    let msg = ExecuteMsg::FundSubscription {
+        asset: "osmo".to_string(),
+        chain: "remote_chain".to_string(),
+        subscriber: "user001".to_string(),
+    };
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[coin(20, "uLuna")]),
+        msg,
+    )
+    .unwrap();
+
+    let res = refresh(&mut deps, &env).unwrap();
+
+    assert_eq!(res.attributes.last().unwrap().value, "1");
+
+    let subscriptions: Vec<(SubscriptionKey, Subscription)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Subscriptions {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(subscriptions[0].1.balance, Some(coin(0, "uLuna")));
+    // The refreshes of atom were paid by the first subscriber
+    assert_eq!(subscriptions[1].1.balance, None);
+    assert_eq!(
+        subscriptions[1].1.last_refresh,
+        Some(env.block.time.minus_seconds(100))
+    );
+    assert_eq!(subscriptions[2].1.balance, Some(coin(15, "uLuna")));
+    assert_eq!(subscriptions[2].1.last_refresh, Some(env.block.time));
+
+    // UNSUBSCRIBE
+
+    let unsubscribe = |subscriber: Option<&str>| ExecuteMsg::Unsubscribe {
+        asset: "osmo".to_string(),
+        chain: "remote_chain".to_string(),
+        subscriber: subscriber.map(str::to_string),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        unsubscribe(Some("user001")),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user001", &[]),
+        unsubscribe(None),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user001".to_string(),
+            amount: vec![coin(15, "uLuna")],
+        })
+    );
+
+    // A subscription saved before the max min interval is never due
+
+    SUBSCRIPTIONS
+        .save(
+            deps.as_mut().storage,
+            (
+                "osmo".to_string(),
+                "remote_chain".to_string(),
+                Addr::unchecked("user001"),
+            ),
+            &Subscription {
+                subscriber: Addr::unchecked("user001"),
+                min_interval: u64::MAX,
+                last_refresh: Some(env.block.time),
+                balance: Some(coin(100, "uLuna")),
+            },
+        )
+        .unwrap();
+
+    let err = refresh(&mut deps, &env).unwrap_err();
+
+    assert!(matches!(err, ContractError::NothingDue {}));
+}
+
+#[test]
+fn remote_price_callback() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let consumer_info = mock_info("consumer000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_oracle_contract".to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "atom".to_string(),
+        feeders: vec![Addr::unchecked("feeder000")],
+        feed_config: None,
+        metadata: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    let request = |asset: &str| ExecuteMsg::RequestRemotePrice {
+        asset: asset.to_string(),
+        chain: "remote_chain".to_string(),
+        callback: to_binary("liquidate").unwrap(),
+    };
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        consumer_info.clone(),
+        request("osmo"),
+    )
+    .unwrap_err();
+
+    let res = execute(deps.as_mut(), env.clone(), consumer_info, request("atom")).unwrap();
+
+    let (queries, callback_msg) = match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_binary(msg).unwrap() {
+            GateExecuteMsg::SendRequests { mut requests, .. } => match requests.pop().unwrap() {
+                GateRequest::Query {
+                    queries,
+                    callback_msg,
+                } => (queries, callback_msg),
+                _ => panic!("Unexpected request"),
+            },
+            _ => panic!("Unexpected msg"),
+        },
+        _ => panic!("Unexpected msg"),
+    };
+
+    assert!(callback_msg.is_some());
+
+    // The consumer receive the price with its callback
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: queries[0].clone(),
+            response: to_binary(&vec![(
+                "atom".to_string(),
+                PriceInfo {
+                    price: Decimal::from_str("12").unwrap(),
+                    time: env.block.time,
+                    height: 1,
+                    source: PriceSource::Local { feeders: vec![] },
+                },
+            )])
+            .unwrap(),
+        }],
+        callback_msg,
+    });
+
+    let res = execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap();
+
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: "consumer000".to_string(),
+                msg: to_binary(&PriceReceiverMsg::RemotePriceCallback {
+                    asset: "atom".to_string(),
+                    chain: "remote_chain".to_string(),
+                    price: PriceInfo {
+                        price: Decimal::from_str("12").unwrap(),
+                        time: env.block.time,
+                        height: env.block.height,
+                        source: PriceSource::Remote {
+                            chain: "remote_chain".to_string()
+                        },
+                    },
+                    msg: to_binary("liquidate").unwrap(),
+                })
+                .unwrap(),
+                funds: vec![],
+            },
+            MsgReplyID::PriceCallback.repr(),
+        )
+    );
+
+    // A failing consumer doesn't revert the response
+
+    let res = reply(
+        deps.as_mut(),
+        env,
+        Reply {
+            id: MsgReplyID::PriceCallback.repr(),
+            result: SubMsgResult::Err("consumer error".to_string()),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "price_callback_failed"),
+            Attribute::new("error", "consumer error")
+        ]
+    );
+}
+
+#[test]
+fn price_guard() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "atom".to_string(),
+        feeders: vec![feeder_info.sender.clone()],
+        feed_config: None,
+        metadata: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let set_guard = |min_price: &str, on_violation: GuardAction| ExecuteMsg::SetPriceGuard {
+        asset: "atom".to_string(),
+        guard: PriceGuard {
+            max_deviation: Some(Decimal::percent(10)),
+            min_price: Some(Decimal::from_str(min_price).unwrap()),
+            max_price: Some(Decimal::from_str("100").unwrap()),
+            on_violation,
+        },
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        feeder_info.clone(),
+        set_guard("1", GuardAction::Reject {}),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        set_guard("101", GuardAction::Reject {}),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::InvalidPriceGuard {}));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        set_guard("1", GuardAction::Reject {}),
+    )
+    .unwrap();
+
+    let feed = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>, price: &str| {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            feeder_info.clone(),
+            ExecuteMsg::FeedPrice {
+                asset: "atom".to_string(),
+                price: Decimal::from_str(price).unwrap(),
+            },
+        )
+    };
+
+    // REJECT
+
+    for price in ["0", "0.5", "200"] {
+        let err = feed(&mut deps, price).unwrap_err();
+
+        assert!(matches!(err, ContractError::PriceOutOfBounds { .. }));
+    }
+
+    feed(&mut deps, "10").unwrap();
+
+    let err = feed(&mut deps, "12").unwrap_err();
+
+    assert!(matches!(err, ContractError::PriceOutOfBounds { .. }));
+
+    feed(&mut deps, "10.5").unwrap();
+
+    // FREEZE
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        set_guard("1", GuardAction::Freeze {}),
+    )
+    .unwrap();
+
+    feed(&mut deps, "20").unwrap();
+
+    let guard: GuardResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Guard {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        guard.frozen,
+        Some(Frozen {
+            price: Decimal::from_str("20").unwrap(),
+            time: env.block.time,
+        })
+    );
+
+    let price_query = QueryMsg::Price {
+        asset: "atom".to_string(),
+        max_age: None,
+        selector: None,
+    };
+
+    let err = query(deps.as_ref(), env.clone(), price_query.clone()).unwrap_err();
+
+    assert!(err.to_string().contains("frozen"));
+
+    let err = feed(&mut deps, "10.5").unwrap_err();
+
+    assert!(matches!(err, ContractError::AssetFrozen { .. }));
+
+    // RESOLVE
+
+    let msg = ExecuteMsg::ResolveFrozen {
+        asset: "atom".to_string(),
+        price: Some(Decimal::from_str("20").unwrap()),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg.clone()).unwrap();
+
+    assert_eq!(
+        from_binary::<Decimal>(&query(deps.as_ref(), env.clone(), price_query).unwrap()).unwrap(),
+        Decimal::from_str("20").unwrap()
+    );
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::AssetNotFrozen { .. }));
+
+    feed(&mut deps, "21").unwrap();
+
+    // A DEVIATION TOO LARGE TO BE REPRESENTED IS A VIOLATION
+
+    let set_unbounded_guard = |on_violation: GuardAction| ExecuteMsg::SetPriceGuard {
+        asset: "atom".to_string(),
+        guard: PriceGuard {
+            max_deviation: Some(Decimal::percent(10)),
+            min_price: None,
+            max_price: None,
+            on_violation,
+        },
+    };
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        set_unbounded_guard(GuardAction::Freeze {}),
+    )
+    .unwrap();
+
+    feed(&mut deps, "0.000000000000000001").unwrap();
+
+    let msg = ExecuteMsg::ResolveFrozen {
+        asset: "atom".to_string(),
+        price: Some(Decimal::from_str("0.000000000000000001").unwrap()),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info,
+        set_unbounded_guard(GuardAction::Reject {}),
+    )
+    .unwrap();
+
+    let err = feed(&mut deps, "100000000000000000000").unwrap_err();
+
+    assert!(matches!(err, ContractError::PriceOutOfBounds { .. }));
+}
+
+#[test]
+fn asset_lifecycle() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+    let new_feeder_info = mock_info("feeder001", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let metadata = AssetMetadata {
+        decimals: 6,
+        base_symbol: "ATOM".to_string(),
+        quote_symbol: "USD".to_string(),
+        description: Some("Cosmos Hub".to_string()),
+    };
+
+    for asset in ["atom", "osmo", "luna"] {
+        let msg = ExecuteMsg::RegisterAsset {
+            asset: asset.to_string(),
+            feeders: vec![feeder_info.sender.clone()],
+            feed_config: None,
+            metadata: (asset == "atom").then(|| metadata.clone()),
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    let asset_info = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>| {
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AssetInfo {
+                asset: "atom".to_string(),
+            },
+        )
+        .map(|res| from_binary::<Asset>(&res).unwrap())
+    };
+
+    assert_eq!(asset_info(&deps).unwrap().metadata, Some(metadata));
+
+    // PAGINATION
+
+    let assets = |start_after: Option<String>| {
+        from_binary::<Vec<(String, Asset)>>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Assets {
+                    start_after,
+                    limit: Some(2),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(asset, _)| asset)
+        .collect::<Vec<String>>()
+    };
+
+    assert_eq!(assets(None), vec!["atom", "luna"]);
+    assert_eq!(assets(Some("luna".to_string())), vec!["osmo"]);
+
+    // UPDATE FEEDER
+
+    let update_feeder =
+        |old_feeder: &MessageInfo, new_feeder: &MessageInfo| ExecuteMsg::UpdateFeeder {
+            asset: "atom".to_string(),
+            old_feeder: old_feeder.sender.clone(),
+            new_feeder: new_feeder.sender.clone(),
+        };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        feeder_info.clone(),
+        update_feeder(&feeder_info, &new_feeder_info),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        update_feeder(&new_feeder_info, &feeder_info),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::DuplicateFeeder { .. }));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        update_feeder(&new_feeder_info, &owner_info),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::FeederNotFound { .. }));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        update_feeder(&feeder_info, &new_feeder_info),
+    )
+    .unwrap();
+
+    let feed_msg = ExecuteMsg::FeedPrice {
+        asset: "atom".to_string(),
+        price: Decimal::from_str("10").unwrap(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        feeder_info.clone(),
+        feed_msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        new_feeder_info.clone(),
+        feed_msg.clone(),
+    )
+    .unwrap();
+
+    // METADATA
+
+    let msg = ExecuteMsg::SetAssetMetadata {
+        asset: "atom".to_string(),
+        metadata: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    assert_eq!(asset_info(&deps).unwrap().metadata, None);
+
+    // REMOVE ASSET
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: Addr::unchecked("gate_contract"),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_oracle_contract".to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::SetRefreshFees {
+        fees: Some(RefreshFees {
+            denom: "uLuna".to_string(),
+            gate_fee: Uint128::from(10_u128),
+            keeper_reward: Uint128::zero(),
+        }),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::Subscribe {
+        asset: "atom".to_string(),
+        chain: "remote_chain".to_string(),
+        min_interval: 100,
+    };
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[coin(30, "uLuna")]),
+        msg,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RemoveAsset {
+        asset: "atom".to_string(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user000".to_string(),
+            amount: vec![coin(30, "uLuna")],
+        })
+    );
+
+    asset_info(&deps).unwrap_err();
+
+    let history: Vec<(u64, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::History {
+                asset: "atom".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(history.is_empty());
+
+    let subscriptions: Vec<(SubscriptionKey, Subscription)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Subscriptions {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(subscriptions.is_empty());
+
+    // The asset can be registered again
+
+    let msg = ExecuteMsg::RegisterAsset {
+        asset: "atom".to_string(),
+        feeders: vec![feeder_info.sender.clone()],
+        feed_config: None,
+        metadata: None,
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+    execute(deps.as_mut(), env, feeder_info, feed_msg).unwrap();
+}
+
+#[test]
+fn cross_rate() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let register_msg = |asset: &str, base: &str, quote: &str| ExecuteMsg::RegisterAsset {
+        asset: asset.to_string(),
+        feeders: vec![feeder_info.sender.clone()],
+        feed_config: None,
+        metadata: Some(AssetMetadata {
+            decimals: 6,
+            base_symbol: base.to_string(),
+            quote_symbol: quote.to_string(),
+            description: None,
+        }),
+    };
+
+    for (asset, base, quote, price) in [
+        ("atom_osmo", "ATOM", "OSMO", "2"),
+        ("osmo_usd", "OSMO", "USD", "0.5"),
+        ("eur_usd", "EUR", "USD", "1.25"),
+    ] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            owner_info.clone(),
+            register_msg(asset, base, quote),
+        )
+        .unwrap();
+
+        let msg = ExecuteMsg::FeedPrice {
+            asset: asset.to_string(),
+            price: Decimal::from_str(price).unwrap(),
+        };
+
+        execute(deps.as_mut(), env.clone(), feeder_info.clone(), msg).unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info,
+        register_msg("atom_osmo_2", "ATOM", "OSMO"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::PairAlredyRegistered { .. }));
+
+    let cross_rate = |base: &str, quote: &str, max_path_length: Option<u32>| {
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::CrossRate {
+                base: base.to_string(),
+                quote: quote.to_string(),
+                max_age: None,
+                max_path_length,
+            },
+        )
+        .map(|res| from_binary::<CrossRateResponse>(&res).unwrap())
+    };
+
+    // ATOM/USD from ATOM/OSMO and OSMO/USD
+
+    let res = cross_rate("ATOM", "USD", None).unwrap();
+
+    assert_eq!(res.price, Decimal::one());
+    assert_eq!(
+        res.path
+            .iter()
+            .map(|step| (step.asset.as_str(), step.inverted))
+            .collect::<Vec<(&str, bool)>>(),
+        vec![("atom_osmo", false), ("osmo_usd", false)]
+    );
+
+    // Inverted pairs
+
+    let res = cross_rate("ATOM", "EUR", None).unwrap();
+
+    assert_eq!(res.price, Decimal::from_str("0.8").unwrap());
+    assert_eq!(res.path.len(), 3);
+    assert!(res.path[2].inverted);
+
+    assert_eq!(
+        cross_rate("USD", "ATOM", None).unwrap().price,
+        Decimal::one()
+    );
+
+    // Bounded path length
+
+    let err = cross_rate("ATOM", "EUR", Some(2)).unwrap_err();
+
+    assert!(err.to_string().contains("No path"));
+
+    // Price derives the rate of the not registered pairs, with the path
+
+    let price = |selector: Option<PriceSelector>| {
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                asset: "ATOM/EUR".to_string(),
+                max_age: None,
+                selector,
+            },
+        )
+        .map(|res| from_binary::<PriceResponse>(&res).unwrap())
+    };
+
+    assert_eq!(
+        price(None).unwrap(),
+        PriceResponse::CrossRate(cross_rate("ATOM", "EUR", None).unwrap())
+    );
+
+    price(Some(PriceSelector::Aggregated {})).unwrap_err();
+}
+
+#[test]
+fn query_errors() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    register_and_feed(
+        &mut deps,
+        env.clone(),
+        owner_info.clone(),
+        feeder_info.clone(),
+        "asset000".to_string(),
+        Decimal::from_str("1.5").unwrap(),
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info,
+        ExecuteMsg::RegisterAsset {
+            asset: "asset001".to_string(),
+            feeders: vec![feeder_info.sender],
+            feed_config: None,
+            metadata: None,
+        },
+    )
+    .unwrap();
+
+    // Never feeded asset
+
+    let err = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Price {
+            asset: "asset001".to_string(),
+            max_age: None,
+            selector: None,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::PriceNeverFeeded {}));
+
+    let err = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Twap {
+            asset: "asset001".to_string(),
+            window_seconds: 60,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::PriceNeverFeeded {}));
+
+    // Not registered asset
+
+    let err = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Price {
+            asset: "asset002".to_string(),
+            max_age: None,
+            selector: None,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Std(_)));
+
+    // Too old
+
+    let mut later_env = env.clone();
+    later_env.block.time = env.block.time.plus_seconds(120);
+
+    let err = query(
+        deps.as_ref(),
+        later_env,
+        QueryMsg::Price {
+            asset: "asset000".to_string(),
+            max_age: Some(60),
+            selector: None,
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::PriceTooOld { age: 120, .. }));
+
+    // Prices skips the never feeded assets and respects the limit
+
+    let prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Prices {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(prices.len(), 1);
+    assert_eq!(prices[0].0, "asset000");
+    assert_eq!(prices[0].1.price, Decimal::from_str("1.5").unwrap());
+
+    let prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::Prices {
+                start_after: None,
+                limit: Some(0),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(prices.is_empty());
+}
+
+#[test]
+fn remote_chains() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let set_permission_msg = |permission: Permission| {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: gate_info.sender.to_string(),
+            msg: to_binary(&GateExecuteMsg::SetPermission {
+                permission,
+                chain: "chain_a".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+
+    let chain = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>| {
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Chain {
+                chain: "chain_a".to_string(),
+            },
+        )
+        .map(|res| from_binary::<ChainResponse>(&res).unwrap())
+    };
+
+    // SEVERAL REMOTE ORACLES
+
+    for contract in ["oracle_a0", "oracle_a1"] {
+        let msg = ExecuteMsg::GateSetPermission {
+            contract: contract.to_string(),
+            chain: "chain_a".to_string(),
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "oracle_b0".to_string(),
+        chain: "chain_b".to_string(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: gate_info.sender.to_string(),
+            msg: to_binary(&GateExecuteMsg::SetPermission {
+                permission: Permission::Permissioned {
+                    addresses: vec!["oracle_b0".to_string()]
+                },
+                chain: "chain_b".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "oracle_a1".to_string(),
+        chain: "chain_a".to_string(),
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::RemoteContractAlredyRegistered { .. }
+    ));
+
+    assert_eq!(
+        chain(&deps).unwrap(),
+        ChainResponse {
+            chain: "chain_a".to_string(),
+            contracts: vec!["oracle_a0".to_string(), "oracle_a1".to_string()],
+            mode: PermissionMode::Permissioned {},
+            legacy_contracts: vec![],
+        }
+    );
+
+    let chains: Vec<ChainResponse> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Chains {
+                start_after: Some("chain_a".to_string()),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(chains.len(), 1);
+    assert_eq!(chains[0].chain, "chain_b");
+
+    // PERMISSION MODE
+
+    let msg = ExecuteMsg::GateSetPermissionMode {
+        chain: "chain_c".to_string(),
+        mode: PermissionMode::Permissionless {},
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::ChainNotRegistered { .. }));
+
+    let msg = ExecuteMsg::GateSetPermissionMode {
+        chain: "chain_a".to_string(),
+        mode: PermissionMode::Permissionless {},
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        set_permission_msg(Permission::Permissionless {})
+    );
+    assert_eq!(
+        chain(&deps).unwrap().mode,
+        PermissionMode::Permissionless {}
+    );
+
+    let msg = ExecuteMsg::GateSetPermissionMode {
+        chain: "chain_a".to_string(),
+        mode: PermissionMode::Permissioned {},
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    // The prices from any registered oracle are accepted
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ExecuteMsg::RegisterAsset {
+            asset: "atom".to_string(),
+            feeders: vec![feeder_info.sender],
+            feed_config: None,
+            metadata: None,
+        },
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: "oracle_a1".to_string(),
+                msg: to_binary(&QueryMsg::PricesByList {
+                    assets: vec!["atom".to_string()],
+                })
+                .unwrap(),
+            }),
+            response: to_binary(&vec![(
+                "atom".to_string(),
+                PriceInfo {
+                    price: Decimal::from_str("12").unwrap(),
+                    time: env.block.time,
+                    height: 1,
+                    source: PriceSource::Local { feeders: vec![] },
+                },
+            )])
+            .unwrap(),
+        }],
+        callback_msg: None,
+    });
+
+    execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    // LEGACY ORACLES
+
+    let msg = ExecuteMsg::SetLegacyContract {
+        contract: "oracle_b0".to_string(),
+        chain: "chain_a".to_string(),
+        legacy: true,
+    };
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::RemoteContractNotRegistered { .. }
+    ));
+
+    let msg = ExecuteMsg::SetLegacyContract {
+        contract: "oracle_a0".to_string(),
+        chain: "chain_a".to_string(),
+        legacy: true,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    assert_eq!(
+        chain(&deps).unwrap().legacy_contracts,
+        vec!["oracle_a0".to_string()]
+    );
+
+    // The legacy oracle is sent the `Price` query, without the new fields
+
+    let msg = ExecuteMsg::FeedRemotePrice {
+        asset: "atom".to_string(),
+        chain: "chain_a".to_string(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("feeder000", &[]), msg).unwrap();
+
+    let (queries, callback_msg) = match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_binary(msg).unwrap() {
+            GateExecuteMsg::SendRequests { mut requests, .. } => match requests.pop().unwrap() {
+                GateRequest::Query {
+                    queries,
+                    callback_msg,
+                } => (queries, callback_msg),
+                _ => panic!("Unexpected request"),
+            },
+            _ => panic!("Unexpected msg"),
+        },
+        _ => panic!("Unexpected msg"),
+    };
+
+    assert_eq!(
+        queries,
+        vec![QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: "oracle_a0".to_string(),
+            msg: Binary::from(br#"{"price":{"asset":"atom"}}"#),
+        })]
+    );
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: queries[0].clone(),
+            response: to_binary(&Decimal::from_str("13").unwrap()).unwrap(),
+        }],
+        callback_msg,
+    });
+
+    execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    let remote_prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(remote_prices[0].1.price, Decimal::from_str("13").unwrap());
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        ExecuteMsg::Subscribe {
+            asset: "atom".to_string(),
+            chain: "chain_a".to_string(),
+            min_interval: 100,
+        },
+    )
+    .unwrap();
+
+    // REMOVE
+
+    let msg = ExecuteMsg::GateRemovePermission {
+        contract: "oracle_a0".to_string(),
+        chain: "chain_a".to_string(),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg.clone()).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        set_permission_msg(Permission::Permissioned {
+            addresses: vec!["oracle_a1".to_string()]
+        })
+    );
+    assert!(chain(&deps).unwrap().legacy_contracts.is_empty());
+
+    let err = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::RemoteContractNotRegistered { .. }
+    ));
+
+    let msg = ExecuteMsg::RemoveChain {
+        chain: "chain_a".to_string(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg.clone()).unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        set_permission_msg(Permission::Permissioned { addresses: vec![] })
+    );
+
+    assert!(matches!(
+        chain(&deps).unwrap_err(),
+        ContractError::ChainNotRegistered { .. }
+    ));
+
+    let remote_prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(remote_prices.is_empty());
+
+    let subscriptions: Vec<(SubscriptionKey, Subscription)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Subscriptions {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert!(subscriptions.is_empty());
+
+    let err = execute(deps.as_mut(), env, owner_info, msg).unwrap_err();
+
+    assert!(matches!(err, ContractError::ChainNotRegistered { .. }));
+}
+
+#[test]
+fn push_prices() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    for contract in ["oracle_a0", "oracle_a1"] {
+        let msg = ExecuteMsg::GateSetPermission {
+            contract: contract.to_string(),
+            chain: "chain_a".to_string(),
+        };
+
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    register_and_feed(
+        &mut deps,
+        env.clone(),
+        owner_info.clone(),
+        feeder_info.clone(),
+        "atom".to_string(),
+        Decimal::from_str("10").unwrap(),
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ExecuteMsg::RegisterAsset {
+            asset: "osmo".to_string(),
+            feeders: vec![feeder_info.sender],
+            feed_config: None,
+            metadata: None,
+        },
+    )
+    .unwrap();
+
+    // PUSH
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper000", &[]),
+        ExecuteMsg::PushPrices {
+            assets: vec!["osmo".to_string()],
+            chains: vec!["chain_a".to_string()],
+        },
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::PriceNeverFeeded {}));
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper000", &[coin(20, "uLuna")]),
+        ExecuteMsg::PushPrices {
+            assets: vec!["atom".to_string(), "osmo".to_string()],
+            chains: vec!["chain_a".to_string()],
+        },
+    )
+    .unwrap();
+
+    let atom_price = PriceInfo {
+        price: Decimal::from_str("10").unwrap(),
+        time: env.block.time,
+        height: env.block.height,
+        source: PriceSource::Local {
+            feeders: vec![Addr::unchecked("feeder000")],
+        },
+    };
+
+    // The never feeded asset is skipped
+
+    let msg = to_binary(&OracleGateMsg::PriceUpdate {
+        prices: vec![("atom".to_string(), atom_price.clone())],
+    })
+    .unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: gate_info.sender.to_string(),
+            msg: to_binary(&GateExecuteMsg::SendRequests {
+                requests: ["oracle_a0", "oracle_a1"]
+                    .into_iter()
+                    .map(|contract| GateRequest::SendMsg {
+                        msg: msg.clone(),
+                        to_contract: contract.to_string(),
+                        send_native: None,
+                    })
+                    .collect(),
+                chain: "chain_a".to_string(),
+                timeout: None,
+            })
+            .unwrap(),
+            funds: vec![coin(20, "uLuna")],
+        })
+    );
+
+    // The legacy oracles are skipped
+
+    let set_legacy = |deps: &mut OwnedDeps<MemoryStorage, MockApi, MockQuerier>,
+                      contract: &str,
+                      legacy: bool| {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            owner_info.clone(),
+            ExecuteMsg::SetLegacyContract {
+                contract: contract.to_string(),
+                chain: "chain_a".to_string(),
+                legacy,
+            },
+        )
+        .unwrap();
+    };
+
+    let push_msg = ExecuteMsg::PushPrices {
+        assets: vec!["atom".to_string()],
+        chains: vec!["chain_a".to_string()],
+    };
+
+    set_legacy(&mut deps, "oracle_a0", true);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper000", &[]),
+        push_msg.clone(),
+    )
+    .unwrap();
+
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg: gate_msg, .. }) => {
+            match from_binary(gate_msg).unwrap() {
+                GateExecuteMsg::SendRequests { requests, .. } => assert_eq!(
+                    requests,
+                    vec![GateRequest::SendMsg {
+                        msg: msg.clone(),
+                        to_contract: "oracle_a1".to_string(),
+                        send_native: None,
+                    }]
+                ),
+                _ => panic!("Unexpected msg"),
+            }
+        }
+        _ => panic!("Unexpected msg"),
+    }
+
+    set_legacy(&mut deps, "oracle_a1", true);
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper000", &[]),
+        push_msg,
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::PushNotSupported { .. }));
+
+    set_legacy(&mut deps, "oracle_a1", false);
+
+    // RECEIVE
+
+    let msg = to_binary(&OracleGateMsg::PriceUpdate {
+        prices: vec![
+            ("atom".to_string(), atom_price.clone()),
+            ("juno".to_string(), atom_price),
+        ],
+    })
+    .unwrap();
+
+    let received_msg = |sender: &str| {
+        ExecuteMsg::ReceiveGateMsg(GateMsg::ReceivedMsg {
+            sender: sender.to_string(),
+            msg: msg.clone(),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        received_msg("oracle_a1"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        received_msg("oracle_b0"),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    let time = env.block.time;
+    env.block.time = env.block.time.plus_seconds(10);
+    env.block.height += 1;
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        gate_info.clone(),
+        received_msg("oracle_a1"),
+    )
+    .unwrap();
+
+    let remote_prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        remote_prices,
+        vec![(
+            "chain_a".to_string(),
+            PriceInfo {
+                price: Decimal::from_str("10").unwrap(),
+                time,
+                height: env.block.height,
+                source: PriceSource::Remote {
+                    chain: "chain_a".to_string()
+                },
+            }
+        )]
+    );
+
+    // An update older than the stored price, received out of order, is ignored
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::ReceivedMsg {
+        sender: "oracle_a1".to_string(),
+        msg: to_binary(&OracleGateMsg::PriceUpdate {
+            prices: vec![(
+                "atom".to_string(),
+                PriceInfo {
+                    price: Decimal::from_str("9").unwrap(),
+                    time: time.minus_seconds(5),
+                    height: env.block.height,
+                    source: PriceSource::Local { feeders: vec![] },
+                },
+            )],
+        })
+        .unwrap(),
+    });
+
+    let res = execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap();
+
+    assert!(!res.attributes.iter().any(|attr| attr.key == "asset"));
+
+    let remote_prices_after: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(remote_prices_after, remote_prices);
+}
+
+#[test]
+fn request_failed() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+    let owner_info = mock_info("onwer000", &[]);
+    let feeder_info = mock_info("feeder000", &[]);
+    let gate_info = mock_info("gate_contract", &[]);
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        InstantiateMsg {},
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RegisterGate {
+        contract: gate_info.sender.clone(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    let msg = ExecuteMsg::GateSetPermission {
+        contract: "remote_oracle_contract".to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner_info.clone(),
+        ExecuteMsg::RegisterAsset {
+            asset: "atom".to_string(),
+            feeders: vec![feeder_info.sender.clone()],
+            feed_config: None,
+            metadata: None,
+        },
+    )
+    .unwrap();
+
+    let gate_request = |res: &Response| match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_binary(msg).unwrap() {
+            GateExecuteMsg::SendRequests { mut requests, .. } => requests.pop().unwrap(),
+            _ => panic!("Unexpected msg"),
+        },
+        _ => panic!("Unexpected msg"),
+    };
+
+    let pending_requests = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>| {
+        from_binary::<Vec<(u64, PendingRequest)>>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PendingRequests {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // REQUEST
+
+    let msg = ExecuteMsg::FeedRemotePrice {
+        asset: "atom".to_string(),
+        chain: "remote_chain".to_string(),
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), feeder_info, msg).unwrap();
+
+    let request = gate_request(&res);
+
+    let mut pending_request = PendingRequest {
+        assets: vec!["atom".to_string()],
+        chain: "remote_chain".to_string(),
+        callback: None,
+        status: RequestStatus::Pending {},
+        sent: env.block.time,
+        attempts: 1,
+    };
+
+    assert_eq!(pending_requests(&deps), vec![(1, pending_request.clone())]);
+
+    let msg = ExecuteMsg::RetryRequest { request_id: 1 };
+
+    let err = execute(deps.as_mut(), env.clone(), mock_info("keeper000", &[]), msg).unwrap_err();
+
+    assert!(matches!(
+        err,
+        ContractError::RequestNotFailed { request_id: 1 }
+    ));
+
+    // FAILED
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::RequestFailed {
+        request: request.clone(),
+    });
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("user000", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+
+    assert!(matches!(err, ContractError::Unauthorized {}));
+
+    execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    pending_request.status = RequestStatus::Failed {};
+
+    assert_eq!(pending_requests(&deps), vec![(1, pending_request.clone())]);
+
+    // The failed pushes are not tracked
+
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::RequestFailed {
+        request: GateRequest::SendMsg {
+            msg: Binary::default(),
+            to_contract: "remote_oracle_contract".to_string(),
+            send_native: None,
+        },
+    });
+
+    execute(deps.as_mut(), env.clone(), gate_info.clone(), msg).unwrap();
+
+    assert_eq!(pending_requests(&deps), vec![(1, pending_request.clone())]);
+
+    // RETRY
+
+    env.block.time = env.block.time.plus_seconds(60);
+
+    let msg = ExecuteMsg::RetryRequest { request_id: 1 };
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("keeper000", &[coin(10, "uLuna")]),
+        msg,
+    )
+    .unwrap();
+
+    // The same request is sent again, with the funds
+
+    assert_eq!(gate_request(&res), request);
+
+    match &res.messages[0].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { funds, .. }) => {
+            assert_eq!(funds, &vec![coin(10, "uLuna")])
+        }
+        _ => panic!("Unexpected msg"),
+    }
+
+    pending_request.status = RequestStatus::Pending {};
+    pending_request.sent = env.block.time;
+    pending_request.attempts = 2;
+
+    assert_eq!(pending_requests(&deps), vec![(1, pending_request)]);
+
+    // The queried oracle is replaced before the response
+
+    for msg in [
+        ExecuteMsg::GateSetPermission {
+            contract: "remote_oracle_contract_1".to_string(),
+            chain: "remote_chain".to_string(),
+        },
+        ExecuteMsg::GateRemovePermission {
+            contract: "remote_oracle_contract".to_string(),
+            chain: "remote_chain".to_string(),
+        },
+    ] {
+        execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+    }
+
+    // RESPONSE
+
+    let (queries, callback_msg) = match request {
+        GateRequest::Query {
+            queries,
+            callback_msg,
+        } => (queries, callback_msg),
+        _ => panic!("Unexpected request"),
+    };
+
+    let price_info = PriceInfo {
+        price: Decimal::from_str("12").unwrap(),
+        time: env.block.time,
+        height: 1,
+        source: PriceSource::Local { feeders: vec![] },
+    };
+
+    // The assets not registered are skipped
+    let msg = ExecuteMsg::ReceiveGateMsg(GateMsg::QueryResponse {
+        queries: vec![GateQueryResponse {
+            request: queries[0].clone(),
+            response: to_binary(&vec![
+                ("atom".to_string(), price_info.clone()),
+                ("osmo".to_string(), price_info),
+            ])
+            .unwrap(),
+        }],
+        callback_msg,
+    });
+
+    let res = execute(deps.as_mut(), env.clone(), gate_info, msg).unwrap();
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "gate_query_response"),
+            Attribute::new("asset", "atom"),
+            Attribute::new("chain", "remote_chain"),
+            Attribute::new("price", "12"),
+        ]
+    );
+
+    assert!(pending_requests(&deps).is_empty());
+
+    let remote_prices: Vec<(String, PriceInfo)> = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::RemotePrices {
+                asset: "atom".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(remote_prices.len(), 1);
+}
+
+#[test]
+fn migrate_chains_contract() {
+    let mut deps = mock_dependencies();
+    let env = mock_env();
+
+    // Entries saved with a single remote oracle, before the lists
+
+    let legacy: Map<String, String> = Map::new("chains_contracts");
+
+    legacy
+        .save(
+            deps.as_mut().storage,
+            "chain_a".to_string(),
+            &"oracle_chain_a".to_string(),
+        )
+        .unwrap();
+
+    CHAINS_CONTRACT
+        .save(
+            deps.as_mut().storage,
+            "chain_b".to_string(),
+            &vec![
+                "oracle_chain_b_0".to_string(),
+                "oracle_chain_b_1".to_string(),
+            ],
+        )
+        .unwrap();
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    assert_eq!(
+        CHAINS_CONTRACT
+            .load(&deps.storage, "chain_a".to_string())
+            .unwrap(),
+        vec!["oracle_chain_a".to_string()]
+    );
+    assert_eq!(
+        CHAINS_CONTRACT
+            .load(&deps.storage, "chain_b".to_string())
+            .unwrap(),
+        vec![
+            "oracle_chain_b_0".to_string(),
+            "oracle_chain_b_1".to_string()
+        ]
+    );
+
+    // Migrating again is a no-op
+
+    migrate(deps.as_mut(), env, MigrateMsg {}).unwrap();
+
+    assert_eq!(
+        CHAINS_CONTRACT
+            .load(&deps.storage, "chain_a".to_string())
+            .unwrap(),
+        vec!["oracle_chain_a".to_string()]
+    );
+}
+
+#[test]
+fn migrate_assets() {
+    let mut deps = mock_dependencies();
+    let mut env = mock_env();
+
+    // Assets saved with a single feeder and the bare price
+
+    #[cosmwasm_schema::cw_serde]
+    struct LegacyAsset {
+        feeder: Addr,
+        price: Option<Decimal>,
+    }
+
+    let legacy: Map<String, LegacyAsset> = Map::new("assets");
+
+    for (asset, price) in [("atom", Some("12")), ("osmo", None)] {
+        legacy
+            .save(
+                deps.as_mut().storage,
+                asset.to_string(),
+                &LegacyAsset {
+                    feeder: Addr::unchecked("feeder000"),
+                    price: price.map(|price| Decimal::from_str(price).unwrap()),
+                },
+            )
+            .unwrap();
+    }
+
+    migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+    let asset_info = |deps: &OwnedDeps<MemoryStorage, MockApi, MockQuerier>, asset: &str| {
+        from_binary::<Asset>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::AssetInfo {
+                    asset: asset.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let atom = Asset {
+        feeders: vec![Addr::unchecked("feeder000")],
+        feed_config: FeedConfig::default(),
+        guard: PriceGuard::default(),
+        frozen: None,
+        metadata: None,
+        price: Some(PriceInfo {
+            price: Decimal::from_str("12").unwrap(),
+            time: env.block.time,
+            height: env.block.height,
+            source: PriceSource::Owner {},
+        }),
+    };
+
+    assert_eq!(asset_info(&deps, "atom"), atom);
+    assert_eq!(asset_info(&deps, "osmo").price, None);
+
+    let price: Decimal = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Price {
+                asset: "atom".to_string(),
+                max_age: None,
+                selector: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(price, Decimal::from_str("12").unwrap());
+
+    // Migrating again is a no-op
+
+    env.block.time = env.block.time.plus_seconds(60);
+
+    migrate(deps.as_mut(), env, MigrateMsg {}).unwrap();
+
+    assert_eq!(asset_info(&deps, "atom"), atom);
 }